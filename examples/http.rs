@@ -6,6 +6,7 @@ use std::str;
 use http::methods::Method;
 use http::client::*;
 
+#[allow(deprecated)]
 fn main() {
 	let mut http = HttpClient::new("www.google.com:80").unwrap();
 	http.set_property(str!("perm"), str!("test"));
@@ -7,6 +7,7 @@ use http::methods::Method;
 use http::client::*;
 
 #[cfg(feature="ssl")]
+#[allow(deprecated)]
 fn main() {
 	let mut http = HttpsClient::new("www.google.com:443").unwrap();
 	http.set_property(str!("perm"), str!("test"));
@@ -0,0 +1,344 @@
+//! Message body readers, bounded by the message framing
+use std::io::{Error, ErrorKind};
+use std::io::prelude::*;
+use std::cmp;
+use std::str::FromStr;
+
+use super::methods::Method;
+use super::encoding::{Coding, Encoder};
+use super::limit::Permit;
+
+/// How the end of a message body is determined
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+	/// The message has no body
+	Empty,
+	/// The body has the given length, in bytes
+	Length(u64),
+	/// The body is sent with the chunked transfer coding
+	Chunked,
+	/// The body ends when the connection is closed
+	UntilClose
+}
+
+impl Framing {
+	/// Determine the framing of a reply to a `method` request, given the reply status `code` and `Transfer-Encoding`
+	/// and `Content-Length` header values, as described in RFC 7230 section 3.3.3
+	pub fn for_reply(method: Method, code: u32, transfer_encoding: Option<&str>, content_length: Option<&str>) -> Result<Framing, Error> {
+		if method == Method::HEAD || (code >= 100 && code < 200) || code == 204 || code == 304 {
+			return Ok(Framing::Empty);
+		}
+		if let Some(te) = transfer_encoding {
+			return match te.rsplit(',').next() {
+				Some(last) if last.trim().eq_ignore_ascii_case("chunked") => Ok(Framing::Chunked),
+				_ => Ok(Framing::UntilClose)
+			};
+		}
+		if let Some(len) = content_length {
+			return match u64::from_str(len.trim()) {
+				Ok(n) => Ok(Framing::Length(n)),
+				Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse number Content-Length from header : {}", e)))
+			};
+		}
+		return Ok(Framing::UntilClose);
+	}
+}
+
+enum State {
+	/// Bytes left to read
+	Length(u64),
+	/// Bytes left in the current chunk. 0 means the next chunk size must be read
+	Chunk(u64),
+	/// The CRLF ending a chunk must be read
+	ChunkEnd,
+	UntilClose,
+	Done
+}
+
+/// A reader over a message body, which returns EOF exactly at the end of the message
+///
+/// If the connection is closed before the end of the body, reading fails with an
+/// `UnexpectedEof` error instead of silently returning a truncated body.
+/// # Example
+/// ```
+/// use std::io::Read;
+/// use http::body::{BodyReader, Framing};
+///
+/// let data = b"5\r\nHello\r\n6\r\n World\r\n0\r\n\r\nNext message";
+/// let mut body = BodyReader::new(&data[..], Framing::Chunked);
+/// let mut s = String::new();
+/// body.read_to_string(&mut s).unwrap();
+/// assert_eq!(s, "Hello World");
+/// ```
+pub struct BodyReader<R: BufRead> {
+	inner: R,
+	state: State,
+	permit: Option<Permit>
+}
+
+impl <R: BufRead> BodyReader<R> {
+	/// Create a new body reader reading from `inner` according to `framing`
+	pub fn new(inner: R, framing: Framing) -> BodyReader<R> {
+		let state = match framing {
+			Framing::Empty | Framing::Length(0) => State::Done,
+			Framing::Length(n) => State::Length(n),
+			Framing::Chunked => State::Chunk(0),
+			Framing::UntilClose => State::UntilClose
+		};
+		return BodyReader{inner: inner, state: state, permit: None};
+	}
+
+	/// Keep a rate limiter `permit` until the whole body has been read, or the reader is dropped,
+	/// so the request is counted in flight until then
+	pub fn hold(&mut self, permit: Permit) {
+		if !self.is_done() {
+			self.permit = Some(permit);
+		}
+	}
+
+	/// Return true if the whole body has been read
+	pub fn is_done(&self) -> bool {
+		return match self.state {
+			State::Done => true,
+			_ => false
+		};
+	}
+
+	/// Get a reference to the underlying reader
+	pub fn get_ref(&self) -> &R {
+		return &self.inner;
+	}
+
+	/// Unwrap this body reader, returning the underlying reader
+	pub fn into_inner(self) -> R {
+		return self.inner;
+	}
+
+	fn read_chunk_size(&mut self) -> Result<u64, Error> {
+		let mut line = String::new();
+		if try!(self.inner.read_line(&mut line)) == 0 {
+			return Err(Error::new(ErrorKind::UnexpectedEof, "Connection closed before end of chunked body"));
+		}
+		let size = line.split(';').next().unwrap_or("").trim();
+		return match u64::from_str_radix(size, 16) {
+			Ok(n) => Ok(n),
+			Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse chunk size : {}", e)))
+		};
+	}
+
+	fn read_trailers(&mut self) -> Result<(), Error> {
+		let mut line = String::new();
+		loop {
+			line.clear();
+			if try!(self.inner.read_line(&mut line)) == 0 || line.trim().is_empty() {
+				return Ok(());
+			}
+		}
+	}
+}
+
+impl <R: BufRead> BufRead for BodyReader<R> {
+	fn fill_buf(&mut self) -> Result<&[u8], Error> {
+		loop {
+			match self.state {
+				State::Length(0) => self.state = State::Done,
+				State::Chunk(0) => {
+					let size = try!(self.read_chunk_size());
+					if size == 0 {
+						try!(self.read_trailers());
+						self.state = State::Done;
+					} else {
+						self.state = State::Chunk(size);
+					}
+				},
+				State::ChunkEnd => {
+					let mut line = String::new();
+					try!(self.inner.read_line(&mut line));
+					if !line.trim().is_empty() {
+						return Err(Error::new(ErrorKind::InvalidData, "Missing CRLF at end of chunk"));
+					}
+					self.state = State::Chunk(0);
+				},
+				_ => break
+			}
+		}
+		let remaining = match self.state {
+			State::Done => {
+				self.permit = None;
+				return Ok(&[]);
+			},
+			State::Length(n) | State::Chunk(n) => Some(n),
+			_ => None
+		};
+		let buf = try!(self.inner.fill_buf());
+		return match remaining {
+			Some(_) if buf.is_empty() => Err(Error::new(ErrorKind::UnexpectedEof, "Connection closed before end of body")),
+			Some(n) => Ok(&buf[..cmp::min(buf.len() as u64, n) as usize]),
+			None => {
+				if buf.is_empty() {
+					self.permit = None;
+				}
+				Ok(buf)
+			}
+		};
+	}
+
+	fn consume(&mut self, amt: usize) {
+		self.inner.consume(amt);
+		self.state = match self.state {
+			State::Length(n) => State::Length(n - amt as u64),
+			State::Chunk(n) if n == amt as u64 => State::ChunkEnd,
+			State::Chunk(n) => State::Chunk(n - amt as u64),
+			State::UntilClose => State::UntilClose,
+			State::ChunkEnd => State::ChunkEnd,
+			State::Done => State::Done
+		};
+	}
+}
+
+impl <R: BufRead> Read for BodyReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		let n = {
+			let available = try!(self.fill_buf());
+			let n = cmp::min(available.len(), buf.len());
+			buf[..n].copy_from_slice(&available[..n]);
+			n
+		};
+		self.consume(n);
+		return Ok(n);
+	}
+}
+
+/// A writer sending data with the chunked transfer coding. Each non empty write is sent as a chunk
+///
+/// When done, `finish()` must be called in order to send the last chunk
+pub struct ChunkedWriter<W: Write> {
+	inner: W
+}
+
+impl <W: Write> ChunkedWriter<W> {
+	/// Create a new chunked writer sending chunks to `inner`
+	pub fn new(inner: W) -> ChunkedWriter<W> {
+		return ChunkedWriter{inner: inner};
+	}
+
+	/// Send the last chunk, flush and return the underlying writer
+	pub fn finish(mut self) -> Result<W, Error> {
+		try!(self.inner.write_all(b"0\r\n\r\n"));
+		try!(self.inner.flush());
+		return Ok(self.inner);
+	}
+}
+
+impl <W: Write> Write for ChunkedWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		try!(write!(self.inner, "{:X}\r\n", buf.len()));
+		try!(self.inner.write_all(buf));
+		try!(self.inner.write_all(b"\r\n"));
+		return Ok(buf.len());
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		return self.inner.flush();
+	}
+}
+
+/// A writer encoding a body on the fly with a content coding, and sending it with the chunked transfer coding
+///
+/// When done, `finish()` must be called in order to terminate the encoded content and send the last chunk
+pub struct EncodedWriter<W: Write> {
+	encoder: Encoder<ChunkedWriter<W>>
+}
+
+impl <W: Write> EncodedWriter<W> {
+	/// Create a new writer encoding content with `coding` and sending it in chunks to `inner`
+	pub fn new(inner: W, coding: Coding) -> Result<EncodedWriter<W>, Error> {
+		return Ok(EncodedWriter{encoder: try!(Encoder::new(ChunkedWriter::new(inner), coding))});
+	}
+
+	/// Terminate the encoded content, send the last chunk, flush and return the underlying writer
+	pub fn finish(self) -> Result<W, Error> {
+		return try!(self.encoder.finish()).finish();
+	}
+}
+
+impl <W: Write> Write for EncodedWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		return self.encoder.write(buf);
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		return self.encoder.flush();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{BufReader, ErrorKind};
+	use std::io::prelude::*;
+	use super::*;
+	use super::super::methods::Method;
+
+	fn read(data: &[u8], framing: Framing) -> Result<(String, String), Error> {
+		// A tiny buffer, so chunks are split across several reads
+		let mut body = BodyReader::new(BufReader::with_capacity(3, data), framing);
+		let mut s = String::new();
+		try!(body.read_to_string(&mut s));
+		assert!(body.is_done() || framing == Framing::UntilClose);
+		let mut rest = String::new();
+		try!(body.into_inner().read_to_string(&mut rest));
+		return Ok((s, rest));
+	}
+
+	#[test]
+	fn framing_of_replies() {
+		assert_eq!(Framing::for_reply(Method::HEAD, 200, None, Some("10")).unwrap(), Framing::Empty);
+		assert_eq!(Framing::for_reply(Method::GET, 204, None, Some("10")).unwrap(), Framing::Empty);
+		assert_eq!(Framing::for_reply(Method::GET, 304, Some("chunked"), None).unwrap(), Framing::Empty);
+		assert_eq!(Framing::for_reply(Method::GET, 200, Some("gzip, Chunked"), Some("10")).unwrap(), Framing::Chunked);
+		assert_eq!(Framing::for_reply(Method::GET, 200, Some("chunked, gzip"), None).unwrap(), Framing::UntilClose);
+		assert_eq!(Framing::for_reply(Method::GET, 200, None, Some(" 10 ")).unwrap(), Framing::Length(10));
+		assert_eq!(Framing::for_reply(Method::GET, 200, None, None).unwrap(), Framing::UntilClose);
+		assert_eq!(Framing::for_reply(Method::GET, 200, None, Some("-1")).unwrap_err().kind(), ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn chunked_body_stops_at_the_end_of_the_message() {
+		let data = b"5;name=value\r\nHello\r\n6\r\n World\r\na\r\n0123456789\r\n0\r\nTrailer: value\r\n\r\nNEXT";
+		assert_eq!(read(data, Framing::Chunked).unwrap(), ("Hello World0123456789".to_string(), "NEXT".to_string()));
+		assert_eq!(read(b"0\r\n\r\nNEXT", Framing::Chunked).unwrap(), (String::new(), "NEXT".to_string()));
+	}
+
+	#[test]
+	fn malformed_chunked_bodies() {
+		assert_eq!(read(b"5\r\nHel", Framing::Chunked).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+		assert_eq!(read(b"5\r\nHello\r\n", Framing::Chunked).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+		assert_eq!(read(b"x\r\nHello\r\n0\r\n\r\n", Framing::Chunked).unwrap_err().kind(), ErrorKind::InvalidData);
+		assert_eq!(read(b"5\r\nHelloWorld\r\n0\r\n\r\n", Framing::Chunked).unwrap_err().kind(), ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn length_and_until_close_bodies() {
+		assert_eq!(read(b"HelloNEXT", Framing::Length(5)).unwrap(), ("Hello".to_string(), "NEXT".to_string()));
+		assert_eq!(read(b"Hel", Framing::Length(5)).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+		assert_eq!(read(b"NEXT", Framing::Empty).unwrap(), (String::new(), "NEXT".to_string()));
+		assert_eq!(read(b"Hello", Framing::UntilClose).unwrap(), ("Hello".to_string(), String::new()));
+	}
+
+	#[test]
+	fn chunked_writer_output_is_read_back() {
+		let mut writer = ChunkedWriter::new(Vec::new());
+		writer.write_all(b"Hello").unwrap();
+		writer.write_all(b"").unwrap();
+		writer.write_all(&[b'x'; 26]).unwrap();
+		let data = writer.finish().unwrap();
+		assert_eq!(&data[..14], b"5\r\nHello\r\n1A\r\n");
+		assert!(data.ends_with(b"\r\n0\r\n\r\n"));
+		let (s, rest) = read(&data, Framing::Chunked).unwrap();
+		assert_eq!(s, format!("Hello{}", "x".repeat(26)));
+		assert!(rest.is_empty());
+	}
+}
@@ -0,0 +1,352 @@
+//! Circuit breaker around upstream hosts
+//!
+//! A `CircuitBreaker` tracks the outcome of requests to each host. After too many failures, the circuit
+//! of the host opens, and requests to it are rejected immediately with a `CircuitOpen` error, instead of
+//! piling up timeouts. After a while, the circuit is half-open : a few probe requests are let through,
+//! and the circuit closes again if they succeed. Like `RateLimiter`, a breaker is a cheap handle on a
+//! shared state, so clones of it can be given to several clients.
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use http::client::*;
+//! use http::methods::Method;
+//! use http::breaker::{CircuitBreaker, CircuitOpen};
+//!
+//! let breaker = CircuitBreaker::new()
+//! 	.consecutive_failures(5)
+//! 	.failure_ratio(0.5, 20)
+//! 	.open_duration(Duration::from_secs(10));
+//! let mut client = HttpClient::new("www.example.com:80").unwrap();
+//! client.set_circuit_breaker(breaker.clone());
+//! match client.send(Method::GET, "/", None, None) {
+//! 	Err(ref e) if CircuitOpen::from_io(e).is_some() => println!("Upstream is down"),
+//! 	_ => {}
+//! }
+//! for (host, state) in breaker.states() {
+//! 	println!("{} : {:?}", host, state);
+//! }
+//! ```
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use std::error;
+use std::fmt;
+
+/// The state of the circuit of a host
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+	/// Requests are sent normally
+	Closed,
+	/// Requests are rejected
+	Open,
+	/// Only a limited number of probe requests are sent
+	HalfOpen
+}
+
+/// The error returned for requests rejected by an open circuit, wrapped in an `io::Error`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitOpen {
+	host: String,
+	retry_in: Duration
+}
+
+impl CircuitOpen {
+	/// Get the host whose circuit is open
+	pub fn get_host(&self) -> &str {
+		return &self.host;
+	}
+
+	/// Get the remaining time before probe requests are let through
+	pub fn get_retry_in(&self) -> Duration {
+		return self.retry_in;
+	}
+
+	/// Get the `CircuitOpen` error wrapped in `error`, if it is one
+	pub fn from_io(error: &io::Error) -> Option<&CircuitOpen> {
+		return error.get_ref().and_then(|e| e.downcast_ref::<CircuitOpen>());
+	}
+}
+
+impl fmt::Display for CircuitOpen {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return write!(f, "Circuit open for {}, retry in {}s", self.host, self.retry_in.as_secs());
+	}
+}
+
+impl error::Error for CircuitOpen {
+	fn description(&self) -> &str {
+		return "Circuit open";
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Config {
+	consecutive: Option<u32>,
+	ratio: Option<(f64, usize)>,
+	window: usize,
+	open_duration: Duration,
+	probes: u32
+}
+
+struct Circuit {
+	state: CircuitState,
+	outcomes: VecDeque<bool>,
+	consecutive_failures: u32,
+	opened_at: Instant,
+	probes: u32
+}
+
+impl Circuit {
+	fn new() -> Circuit {
+		return Circuit{state: CircuitState::Closed, outcomes: VecDeque::new(), consecutive_failures: 0, opened_at: Instant::now(), probes: 0};
+	}
+
+	fn open(&mut self) {
+		self.state = CircuitState::Open;
+		self.opened_at = Instant::now();
+		self.probes = 0;
+	}
+
+	fn close(&mut self) {
+		self.state = CircuitState::Closed;
+		self.outcomes.clear();
+		self.consecutive_failures = 0;
+		self.probes = 0;
+	}
+
+	/// Move from open to half-open state once the open duration has elapsed
+	fn update(&mut self, config: &Config) {
+		if self.state == CircuitState::Open && self.opened_at.elapsed() >= config.open_duration {
+			self.state = CircuitState::HalfOpen;
+			self.probes = 0;
+		}
+	}
+
+	fn should_trip(&self, config: &Config) -> bool {
+		if config.consecutive.map(|n| self.consecutive_failures >= n).unwrap_or(false) {
+			return true;
+		}
+		if let Some((ratio, min_requests)) = config.ratio {
+			let failures = self.outcomes.iter().filter(|s| !**s).count();
+			return self.outcomes.len() >= min_requests && failures as f64 >= ratio * self.outcomes.len() as f64;
+		}
+		return false;
+	}
+}
+
+struct Inner {
+	config: Config,
+	circuits: HashMap<String, Circuit>
+}
+
+/// A circuit breaker, keeping a circuit per host, shared by all its clones
+#[derive(Clone)]
+pub struct CircuitBreaker {
+	inner: Arc<Mutex<Inner>>
+}
+
+impl CircuitBreaker {
+	/// Create a new circuit breaker, opening circuits after 5 consecutive failures for 30 seconds,
+	/// then letting a single probe request through
+	pub fn new() -> CircuitBreaker {
+		let config = Config{consecutive: Some(5), ratio: None, window: 20, open_duration: Duration::from_secs(30), probes: 1};
+		return CircuitBreaker{inner: Arc::new(Mutex::new(Inner{config: config, circuits: HashMap::new()}))};
+	}
+
+	fn lock(&self) -> MutexGuard<Inner> {
+		return match self.inner.lock() {
+			Ok(i) => i,
+			Err(e) => e.into_inner()
+		};
+	}
+
+	/// Open circuits after `failures` consecutive failures
+	pub fn consecutive_failures(self, failures: u32) -> CircuitBreaker {
+		self.lock().config.consecutive = Some(failures);
+		return self;
+	}
+
+	/// Open circuits when the ratio of failures among the last requests reaches `ratio`, between 0 and 1.
+	/// The ratio is only considered once at least `min_requests` outcomes are known
+	pub fn failure_ratio(self, ratio: f64, min_requests: usize) -> CircuitBreaker {
+		{
+			let mut inner = self.lock();
+			inner.config.ratio = Some((ratio, min_requests));
+			if inner.config.window < min_requests {
+				inner.config.window = min_requests;
+			}
+		}
+		return self;
+	}
+
+	/// Set the number of last outcomes the failure ratio is computed on. Defaults to 20
+	pub fn window(self, requests: usize) -> CircuitBreaker {
+		self.lock().config.window = requests;
+		return self;
+	}
+
+	/// Set how long circuits stay open before letting probe requests through
+	pub fn open_duration(self, duration: Duration) -> CircuitBreaker {
+		self.lock().config.open_duration = duration;
+		return self;
+	}
+
+	/// Set the number of concurrent probe requests let through in half-open state
+	pub fn half_open_probes(self, probes: u32) -> CircuitBreaker {
+		self.lock().config.probes = probes;
+		return self;
+	}
+
+	/// Check whether a request to `host` can be sent. Fails with a `CircuitOpen` error if the circuit is open,
+	/// or if it is half-open and enough probe requests are already in flight.
+	///
+	/// Every allowed request must be followed by a call to `record` with its outcome, or to `cancel` if it is not sent.
+	pub fn allow(&self, host: &str) -> Result<(), io::Error> {
+		let mut inner = self.lock();
+		let config = inner.config;
+		let circuit = inner.circuits.entry(host.to_string()).or_insert_with(Circuit::new);
+		circuit.update(&config);
+		let retry_in = match circuit.state {
+			CircuitState::Closed => return Ok(()),
+			CircuitState::HalfOpen if circuit.probes < config.probes => {
+				circuit.probes += 1;
+				return Ok(());
+			},
+			CircuitState::HalfOpen => Duration::from_secs(0),
+			CircuitState::Open => config.open_duration.checked_sub(circuit.opened_at.elapsed()).unwrap_or(Duration::from_secs(0))
+		};
+		return Err(io::Error::new(ErrorKind::Other, CircuitOpen{host: host.to_string(), retry_in: retry_in}));
+	}
+
+	/// Record the outcome of a request to `host`
+	pub fn record(&self, host: &str, success: bool) {
+		let mut inner = self.lock();
+		let config = inner.config;
+		let circuit = inner.circuits.entry(host.to_string()).or_insert_with(Circuit::new);
+		match circuit.state {
+			CircuitState::HalfOpen if success => circuit.close(),
+			CircuitState::HalfOpen => circuit.open(),
+			// Outcomes of requests sent before the circuit opened are ignored
+			CircuitState::Open => {},
+			CircuitState::Closed => {
+				circuit.outcomes.push_back(success);
+				while circuit.outcomes.len() > config.window {
+					circuit.outcomes.pop_front();
+				}
+				circuit.consecutive_failures = if success { 0 } else { circuit.consecutive_failures + 1 };
+				if circuit.should_trip(&config) {
+					circuit.open();
+				}
+			}
+		}
+	}
+
+	/// Give back the permission of a request to `host` allowed by `allow` but finally not sent
+	pub fn cancel(&self, host: &str) {
+		if let Some(c) = self.lock().circuits.get_mut(host) {
+			if c.state == CircuitState::HalfOpen && c.probes > 0 {
+				c.probes -= 1;
+			}
+		}
+	}
+
+	/// Get the state of the circuit of `host`
+	pub fn state(&self, host: &str) -> CircuitState {
+		let mut inner = self.lock();
+		let config = inner.config;
+		return match inner.circuits.get_mut(host) {
+			Some(c) => {
+				c.update(&config);
+				c.state
+			},
+			None => CircuitState::Closed
+		};
+	}
+
+	/// Get the state of the circuits of all the hosts requests have been sent to
+	pub fn states(&self) -> Vec<(String, CircuitState)> {
+		let mut inner = self.lock();
+		let config = inner.config;
+		return inner.circuits.iter_mut().map(|(h, c)| {
+			c.update(&config);
+			(h.clone(), c.state)
+		}).collect();
+	}
+
+	/// Close the circuit of `host`, forgetting its past failures
+	pub fn reset(&self, host: &str) {
+		if let Some(c) = self.lock().circuits.get_mut(host) {
+			c.close();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+	use std::time::Duration;
+	use super::*;
+
+	fn open(breaker: &CircuitBreaker, host: &str) -> bool {
+		return breaker.allow(host).err().map(|e| CircuitOpen::from_io(&e).is_some()).unwrap_or(false);
+	}
+
+	#[test]
+	fn consecutive_failures() {
+		let breaker = CircuitBreaker::new().consecutive_failures(3);
+		for &success in &[false, false, true, false, false] {
+			breaker.allow("a").unwrap();
+			breaker.record("a", success);
+		}
+		assert_eq!(breaker.state("a"), CircuitState::Closed);
+		breaker.allow("a").unwrap();
+		breaker.record("a", false);
+		assert_eq!(breaker.state("a"), CircuitState::Open);
+		assert!(open(&breaker, "a"));
+		assert_eq!(breaker.state("b"), CircuitState::Closed);
+		breaker.reset("a");
+		assert!(breaker.allow("a").is_ok());
+	}
+
+	#[test]
+	fn failure_ratio() {
+		let breaker = CircuitBreaker::new().consecutive_failures(100).failure_ratio(0.5, 4);
+		for &success in &[false, true, false] {
+			breaker.record("a", success);
+		}
+		assert_eq!(breaker.state("a"), CircuitState::Closed);
+		breaker.record("a", true);
+		assert_eq!(breaker.state("a"), CircuitState::Open);
+		// Old outcomes leave the window
+		for &(window, state) in &[(4, CircuitState::Closed), (20, CircuitState::Open)] {
+			let breaker = CircuitBreaker::new().consecutive_failures(100).failure_ratio(0.6, 4).window(window);
+			for &success in &[false, true, false, true, false] {
+				breaker.record("a", success);
+			}
+			assert_eq!(breaker.state("a"), state);
+		}
+	}
+
+	#[test]
+	fn half_open_probes() {
+		let breaker = CircuitBreaker::new().consecutive_failures(1).open_duration(Duration::from_millis(20)).half_open_probes(1);
+		breaker.record("a", false);
+		let retry_in = breaker.allow("a").err().map(|e| CircuitOpen::from_io(&e).unwrap().get_retry_in()).unwrap();
+		assert!(retry_in <= Duration::from_millis(20));
+		thread::sleep(Duration::from_millis(30));
+		assert_eq!(breaker.state("a"), CircuitState::HalfOpen);
+		breaker.allow("a").unwrap();
+		assert!(open(&breaker, "a"));
+		// A probe which is not sent lets another one through
+		breaker.cancel("a");
+		breaker.allow("a").unwrap();
+		breaker.record("a", false);
+		assert_eq!(breaker.state("a"), CircuitState::Open);
+		thread::sleep(Duration::from_millis(30));
+		breaker.allow("a").unwrap();
+		breaker.record("a", true);
+		assert_eq!(breaker.state("a"), CircuitState::Closed);
+		assert_eq!(breaker.states(), vec![("a".to_string(), CircuitState::Closed)]);
+	}
+}
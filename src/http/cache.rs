@@ -0,0 +1,661 @@
+//! HTTP caching layer, as described in RFC 9111
+//!
+//! A `CachingClient` stands in front of any `Execute` implementation, and behaves as a private cache :
+//! replies to `GET` requests are stored according to their `Cache-Control`, `Expires` and `Vary` properties,
+//! fresh replies are served without contacting the server, and stale ones are revalidated with conditional
+//! requests. The cache status of every reply is available with `HttpReply::get_cache_status()`.
+//! # Example
+//! ```no_run
+//! use http::client::*;
+//! use http::methods::Method;
+//! use http::request::HttpRequest;
+//! use http::cache::{CachingClient, MemoryCache};
+//!
+//! let client = HttpClient::new("www.example.com:80").unwrap();
+//! let mut cached = CachingClient::new(client, MemoryCache::new());
+//! let request = HttpRequest::new(Method::GET, "/resource");
+//! let first = cached.execute(&request).unwrap();
+//! let second = cached.execute(&request).unwrap();
+//! println!("{:?} then {:?}", first.get_cache_status(), second.get_cache_status());
+//! ```
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{Error, ErrorKind, BufRead, BufReader, Write};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::client::{Execute, WithHeader};
+use super::messages::{HttpReply, BufferedReply};
+use super::request::HttpRequest;
+use super::methods::Method;
+use super::headers::{Header, CacheControl, ETag, LastModified, Date, Expires, Vary};
+use super::constants::properties;
+
+/// How a reply has been obtained by a `CachingClient`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CacheStatus {
+	/// The reply has been served from the cache, without contacting the server
+	Hit,
+	/// The reply has been fetched from the server
+	Miss,
+	/// The cached reply has been validated by the server with a `304 Not Modified` reply
+	Revalidated,
+	/// The cached reply is stale, but has been served because the server could not be reached
+	Stale
+}
+
+/// Status codes whose replies can be cached without explicit freshness information
+const HEURISTICALLY_CACHEABLE: [u32; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Properties of a `304 Not Modified` reply which must not update a cached reply
+const NOT_UPDATED: [&'static str; 4] = [properties::CONTENT_LENGTH, properties::CONTENT_ENCODING, properties::TRANSFER_ENCODING, properties::CONTENT_RANGE];
+
+/// Largest `Age` value, in seconds, larger values being clamped to it (RFC 9111 section 1.2.2)
+const MAX_AGE_VALUE: u64 = 1 << 31;
+
+/// Saturating duration from `earlier` to `later`
+fn elapsed(later: SystemTime, earlier: SystemTime) -> Duration {
+	return later.duration_since(earlier).unwrap_or(Duration::from_secs(0));
+}
+
+/// Saturating sum of durations
+fn add(a: Duration, b: Duration) -> Duration {
+	return a.checked_add(b).unwrap_or(Duration::new(u64::max_value(), 999999999));
+}
+
+fn find<'a, I: Iterator<Item=(&'a String, &'a String)>>(mut iter: I, key: &str) -> Option<&'a String> {
+	return iter.find(|&(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v);
+}
+
+/// A cached reply, with the information needed to compute its freshness
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+	version: String,
+	code: u32,
+	status: String,
+	header: HashMap<String, String>,
+	body: Vec<u8>,
+	request_time: SystemTime,
+	response_time: SystemTime,
+	vary: Vec<(String, Option<String>)>
+}
+
+impl CacheEntry {
+	fn new(request: &HttpRequest, reply: &BufferedReply, request_time: SystemTime, response_time: SystemTime) -> CacheEntry {
+		let vary = match reply.get_typed::<Vary>() {
+			Some(v) => v.0.iter().map(|name| (name.to_ascii_lowercase(), find(request.iter(), name).cloned())).collect(),
+			None => Vec::new()
+		};
+		return CacheEntry {
+			version: reply.get_version().clone(),
+			code: reply.get_code(),
+			status: reply.get_status().clone(),
+			header: reply.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+			body: reply.get_body().to_vec(),
+			request_time: request_time,
+			response_time: response_time,
+			vary: vary
+		};
+	}
+
+	fn typed<H: Header>(&self) -> Option<H> {
+		return find(self.header.iter(), H::name()).and_then(|v| H::parse(v).ok());
+	}
+
+	fn cache_control(&self) -> CacheControl {
+		return self.typed::<CacheControl>().unwrap_or(CacheControl::new());
+	}
+
+	/// Return true if the properties selected by `Vary` have the same values in `request`
+	fn matches(&self, request: &HttpRequest) -> bool {
+		return self.vary.iter().all(|&(ref name, ref value)| find(request.iter(), name) == value.as_ref());
+	}
+
+	fn date(&self) -> SystemTime {
+		return self.typed::<Date>().map(|d| SystemTime::from(d.0)).unwrap_or(self.response_time);
+	}
+
+	/// Compute the freshness lifetime, from explicit freshness information, or heuristically
+	fn freshness_lifetime(&self) -> Duration {
+		let cc = self.cache_control();
+		if let Some(max_age) = cc.max_age() {
+			return max_age;
+		}
+		if find(self.header.iter(), properties::EXPIRES).is_some() {
+			return match self.typed::<Expires>() {
+				Some(e) => elapsed(SystemTime::from(e.0), self.date()),
+				None => Duration::from_secs(0)
+			};
+		}
+		if HEURISTICALLY_CACHEABLE.contains(&self.code) || cc.is_public() {
+			if let Some(lm) = self.typed::<LastModified>() {
+				return elapsed(self.date(), SystemTime::from(lm.0)) / 10;
+			}
+		}
+		return Duration::from_secs(0);
+	}
+
+	/// Compute the current age of the entry
+	fn age(&self, now: SystemTime) -> Duration {
+		let age_value = find(self.header.iter(), properties::AGE)
+			.and_then(|a| u64::from_str(a.trim()).ok())
+			.map(|a| Duration::from_secs(if a > MAX_AGE_VALUE { MAX_AGE_VALUE } else { a }))
+			.unwrap_or(Duration::from_secs(0));
+		let apparent_age = elapsed(self.response_time, self.date());
+		let corrected_age = add(age_value, elapsed(self.response_time, self.request_time));
+		let initial_age = if apparent_age > corrected_age { apparent_age } else { corrected_age };
+		return add(initial_age, elapsed(now, self.response_time));
+	}
+
+	/// Return true if the entry can be served without validation, according to the request directives
+	fn is_acceptable(&self, now: SystemTime, request: &CacheControl) -> bool {
+		let cc = self.cache_control();
+		if cc.no_cache() || request.no_cache() {
+			return false;
+		}
+		let (age, lifetime) = (self.age(now), self.freshness_lifetime());
+		if request.max_age().map(|m| age > m).unwrap_or(false) {
+			return false;
+		}
+		if let Some(min_fresh) = request.min_fresh() {
+			return lifetime > add(age, min_fresh);
+		}
+		if lifetime > age {
+			return true;
+		}
+		if request.has("max-stale") && !cc.must_revalidate() {
+			return request.max_stale().map(|m| age - lifetime <= m).unwrap_or(true);
+		}
+		return false;
+	}
+
+	/// Return true if the entry may be served stale when the server cannot be reached
+	fn can_serve_stale(&self) -> bool {
+		let cc = self.cache_control();
+		return !cc.must_revalidate() && !cc.no_cache();
+	}
+
+	/// Update the entry with the properties of a `304 Not Modified` reply
+	fn update(&mut self, reply: &BufferedReply, request_time: SystemTime, response_time: SystemTime) {
+		for (k, v) in reply.iter() {
+			if NOT_UPDATED.iter().any(|n| n.eq_ignore_ascii_case(k)) {
+				continue;
+			}
+			self.header.retain(|name, _| !name.eq_ignore_ascii_case(k));
+			self.header.insert(k.clone(), v.clone());
+		}
+		self.request_time = request_time;
+		self.response_time = response_time;
+	}
+
+	/// Build a reply from the entry, with its current `Age`
+	fn to_reply(&self, now: SystemTime, status: CacheStatus) -> BufferedReply {
+		let mut reply = HttpReply::from_parts(&self.version, self.code, &self.status, self.header.clone(), self.body.clone());
+		reply.unset_property(properties::AGE);
+		reply.set_property(properties::AGE.to_string(), self.age(now).as_secs().to_string());
+		reply.set_cache_status(status);
+		return reply;
+	}
+
+	/// Serialize the entry to `writer`
+	pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), Error> {
+		let secs = |t: SystemTime| elapsed(t, UNIX_EPOCH).as_secs();
+		try!(write!(writer, "{} {}\n{} {} {}\n", secs(self.request_time), secs(self.response_time), self.version, self.code, self.status));
+		for &(ref name, ref value) in &self.vary {
+			try!(match *value {
+				Some(ref v) => write!(writer, "V {}: {}\n", name, v),
+				None => write!(writer, "V {}\n", name)
+			});
+		}
+		for (k, v) in &self.header {
+			try!(write!(writer, "H {}: {}\n", k, v));
+		}
+		try!(writer.write_all(b"\n"));
+		return writer.write_all(&self.body);
+	}
+
+	/// Deserialize an entry written with `write_to`
+	pub fn read_from<R: BufRead + ?Sized>(reader: &mut R) -> Result<CacheEntry, Error> {
+		let invalid = || Error::new(ErrorKind::InvalidData, "Invalid cache entry");
+		let mut line = String::new();
+		try!(reader.read_line(&mut line));
+		let times: Vec<u64> = line.split_whitespace().filter_map(|t| u64::from_str(t).ok()).collect();
+		if times.len() != 2 {
+			return Err(invalid());
+		}
+		line.clear();
+		try!(reader.read_line(&mut line));
+		let mut splt = line.trim_end().splitn(3, ' ');
+		let version = try!(splt.next().ok_or_else(&invalid)).to_string();
+		let code = try!(splt.next().and_then(|c| u32::from_str(c).ok()).ok_or_else(&invalid));
+		let status = splt.next().unwrap_or("").to_string();
+		let mut entry = CacheEntry {
+			version: version,
+			code: code,
+			status: status,
+			header: HashMap::new(),
+			body: Vec::new(),
+			request_time: try!(UNIX_EPOCH.checked_add(Duration::from_secs(times[0])).ok_or_else(&invalid)),
+			response_time: try!(UNIX_EPOCH.checked_add(Duration::from_secs(times[1])).ok_or_else(&invalid)),
+			vary: Vec::new()
+		};
+		loop {
+			line.clear();
+			if try!(reader.read_line(&mut line)) == 0 {
+				return Err(invalid());
+			}
+			let l = line.trim_end_matches(|c| c == '\r' || c == '\n');
+			if l.is_empty() {
+				break;
+			}
+			let (kind, rest) = (try!(l.get(..1).ok_or_else(&invalid)), l.get(2..).unwrap_or(""));
+			let mut splt = rest.splitn(2, ": ");
+			let name = splt.next().unwrap_or("").to_string();
+			match kind {
+				"V" => entry.vary.push((name, splt.next().map(|v| v.to_string()))),
+				"H" => { entry.header.insert(name, try!(splt.next().ok_or_else(&invalid)).to_string()); },
+				_ => return Err(invalid())
+			}
+		}
+		try!(reader.read_to_end(&mut entry.body));
+		return Ok(entry);
+	}
+}
+
+/// A storage backend for cached replies
+pub trait CacheStorage {
+	/// Get the entry stored for `key`
+	fn get(&mut self, key: &str) -> Option<CacheEntry>;
+
+	/// Store `entry` for `key`, replacing any previous one
+	fn put(&mut self, key: &str, entry: CacheEntry) -> Result<(), Error>;
+
+	/// Remove the entry stored for `key`, if any
+	fn remove(&mut self, key: &str) -> Result<(), Error>;
+}
+
+/// In-memory cache storage, with an optional maximum number of entries
+pub struct MemoryCache {
+	entries: HashMap<String, CacheEntry>,
+	capacity: Option<usize>
+}
+
+impl MemoryCache {
+	/// Create a new unbounded memory storage
+	pub fn new() -> MemoryCache {
+		return MemoryCache{entries: HashMap::new(), capacity: None};
+	}
+
+	/// Create a new memory storage holding at most `capacity` entries. The oldest entries are evicted first
+	pub fn with_capacity(capacity: usize) -> MemoryCache {
+		return MemoryCache{entries: HashMap::new(), capacity: Some(capacity)};
+	}
+}
+
+impl CacheStorage for MemoryCache {
+	fn get(&mut self, key: &str) -> Option<CacheEntry> {
+		return self.entries.get(key).cloned();
+	}
+
+	fn put(&mut self, key: &str, entry: CacheEntry) -> Result<(), Error> {
+		if let Some(capacity) = self.capacity {
+			while self.entries.len() >= capacity && !self.entries.contains_key(key) {
+				let oldest = match self.entries.iter().min_by_key(|&(_, e)| e.response_time) {
+					Some((k, _)) => k.clone(),
+					None => return Ok(())
+				};
+				self.entries.remove(&oldest);
+			}
+		}
+		self.entries.insert(key.to_string(), entry);
+		return Ok(());
+	}
+
+	fn remove(&mut self, key: &str) -> Result<(), Error> {
+		self.entries.remove(key);
+		return Ok(());
+	}
+}
+
+/// On-disk cache storage, holding one file per entry in a directory
+pub struct DiskCache {
+	dir: PathBuf
+}
+
+impl DiskCache {
+	/// Create a new disk storage in `dir`, which is created if it doesn't exist
+	pub fn new<P: AsRef<Path>>(dir: P) -> Result<DiskCache, Error> {
+		try!(fs::create_dir_all(dir.as_ref()));
+		return Ok(DiskCache{dir: dir.as_ref().to_path_buf()});
+	}
+
+	fn path(&self, key: &str) -> PathBuf {
+		let mut hasher = DefaultHasher::new();
+		key.hash(&mut hasher);
+		return self.dir.join(format!("{:016x}.cache", hasher.finish()));
+	}
+}
+
+impl CacheStorage for DiskCache {
+	fn get(&mut self, key: &str) -> Option<CacheEntry> {
+		let mut reader = BufReader::new(match File::open(self.path(key)) {
+			Ok(f) => f,
+			Err(_) => return None
+		});
+		let mut stored_key = String::new();
+		if reader.read_line(&mut stored_key).is_err() || stored_key.trim_end_matches('\n') != key {
+			return None;
+		}
+		return CacheEntry::read_from(&mut reader).ok();
+	}
+
+	fn put(&mut self, key: &str, entry: CacheEntry) -> Result<(), Error> {
+		let path = self.path(key);
+		let tmp = path.with_extension("tmp");
+		{
+			let mut file = try!(File::create(&tmp));
+			try!(write!(file, "{}\n", key));
+			try!(entry.write_to(&mut file));
+		}
+		return fs::rename(tmp, path);
+	}
+
+	fn remove(&mut self, key: &str) -> Result<(), Error> {
+		return match fs::remove_file(self.path(key)) {
+			Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+			r => r
+		};
+	}
+}
+
+/// A private HTTP cache in front of an `Execute` implementation
+///
+/// Only `GET` requests are served from the cache. Requests which already carry conditional or
+/// `Range` properties are always forwarded. Successful replies to unsafe methods invalidate the
+/// entry cached for their target. Errors from the storage are ignored, and never make requests fail.
+pub struct CachingClient<C: Execute, S: CacheStorage> {
+	client: C,
+	storage: S,
+	namespace: String
+}
+
+impl <C: Execute, S: CacheStorage> CachingClient<C, S> {
+	/// Create a new caching layer in front of `client`, storing replies in `storage`
+	pub fn new(client: C, storage: S) -> CachingClient<C, S> {
+		return CachingClient{client: client, storage: storage, namespace: String::new()};
+	}
+
+	/// Set a namespace prefixing storage keys. Keys already include the host requests are sent to, so a
+	/// namespace is only needed to keep apart clients of the same host
+	pub fn set_namespace(&mut self, namespace: &str) {
+		self.namespace = namespace.to_string();
+	}
+
+	/// Get a reference to the wrapped client
+	pub fn get_ref(&self) -> &C {
+		return &self.client;
+	}
+
+	/// Get a mutable reference to the wrapped client
+	pub fn get_mut(&mut self) -> &mut C {
+		return &mut self.client;
+	}
+
+	/// Get a mutable reference to the storage
+	pub fn get_storage(&mut self) -> &mut S {
+		return &mut self.storage;
+	}
+
+	/// Unwrap the caching layer, returning the wrapped client
+	pub fn into_inner(self) -> C {
+		return self.client;
+	}
+
+	/// Key of the entry of `request`, made of the namespace, the host the request is sent to, and its target
+	fn key(&self, request: &HttpRequest) -> String {
+		let host = match find(request.iter(), properties::HOST) {
+			Some(h) => Some(h.to_ascii_lowercase()),
+			None => self.client.get_host().map(|h| h.to_ascii_lowercase())
+		};
+		return format!("{}{}{}", self.namespace, host.unwrap_or(String::new()), request.get_target());
+	}
+
+	fn is_storable(request: &HttpRequest, reply: &BufferedReply) -> bool {
+		let cc = reply.get_typed::<CacheControl>().unwrap_or(CacheControl::new());
+		if cc.no_store() || request.get_typed::<CacheControl>().map(|c| c.no_store()).unwrap_or(false) {
+			return false;
+		}
+		if reply.get_typed::<Vary>().map(|v| v.is_any()).unwrap_or(false) {
+			return false;
+		}
+		let explicit = cc.max_age().is_some() || cc.is_public() || find(reply.iter(), properties::EXPIRES).is_some();
+		return reply.get_code() != 206 && reply.get_code() >= 200 && (explicit || HEURISTICALLY_CACHEABLE.contains(&reply.get_code()));
+	}
+
+	/// Send `request` to the server, and store the reply if it is storable
+	fn fetch(&mut self, key: &str, request: &HttpRequest) -> Result<BufferedReply, Error> {
+		let request_time = SystemTime::now();
+		let mut reply = try!(self.client.execute(request));
+		if CachingClient::<C, S>::is_storable(request, &reply) {
+			let _ = self.storage.put(key, CacheEntry::new(request, &reply, request_time, SystemTime::now()));
+		}
+		reply.set_cache_status(CacheStatus::Miss);
+		return Ok(reply);
+	}
+
+	/// Validate a stored entry with a conditional request
+	fn revalidate(&mut self, key: &str, request: &HttpRequest, mut entry: CacheEntry) -> Result<BufferedReply, Error> {
+		let mut conditional = request.clone();
+		if let Some(etag) = entry.typed::<ETag>() {
+			conditional.set_property(properties::IF_NONE_MATCH.to_string(), etag.to_string());
+		}
+		if let Some(lm) = entry.typed::<LastModified>() {
+			conditional.set_property(properties::IF_MODIFIED_SINCE.to_string(), lm.to_string());
+		}
+		let request_time = SystemTime::now();
+		let reply = match self.client.execute(&conditional) {
+			Ok(r) => r,
+			Err(_) if entry.can_serve_stale() => return Ok(entry.to_reply(SystemTime::now(), CacheStatus::Stale)),
+			Err(e) => return Err(e)
+		};
+		if reply.get_code() != 304 {
+			return self.store_fetched(key, request, reply, request_time);
+		}
+		let now = SystemTime::now();
+		entry.update(&reply, request_time, now);
+		let revalidated = entry.to_reply(now, CacheStatus::Revalidated);
+		let _ = self.storage.put(key, entry);
+		return Ok(revalidated);
+	}
+
+	fn store_fetched(&mut self, key: &str, request: &HttpRequest, mut reply: BufferedReply, request_time: SystemTime) -> Result<BufferedReply, Error> {
+		if CachingClient::<C, S>::is_storable(request, &reply) {
+			let _ = self.storage.put(key, CacheEntry::new(request, &reply, request_time, SystemTime::now()));
+		} else {
+			let _ = self.storage.remove(key);
+		}
+		reply.set_cache_status(CacheStatus::Miss);
+		return Ok(reply);
+	}
+}
+
+impl <C: Execute, S: CacheStorage> Execute for CachingClient<C, S> {
+	fn get_host(&self) -> Option<String> {
+		return self.client.get_host();
+	}
+
+	fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+		let key = self.key(request);
+		let conditional = [properties::IF_NONE_MATCH, properties::IF_MODIFIED_SINCE, properties::IF_MATCH, properties::IF_RANGE, properties::RANGE]
+			.iter().any(|p| find(request.iter(), p).is_some());
+		match request.get_method() {
+			Method::GET if !conditional => {},
+			Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::CONNECT => {
+				let mut reply = try!(self.client.execute(request));
+				reply.set_cache_status(CacheStatus::Miss);
+				return Ok(reply);
+			},
+			_ => {
+				let mut reply = try!(self.client.execute(request));
+				if reply.get_code() < 400 {
+					let _ = self.storage.remove(&key);
+				}
+				reply.set_cache_status(CacheStatus::Miss);
+				return Ok(reply);
+			}
+		}
+		let entry = match self.storage.get(&key) {
+			Some(e) => if e.matches(request) { e } else { return self.fetch(&key, request) },
+			None => return self.fetch(&key, request)
+		};
+		let now = SystemTime::now();
+		let request_cc = request.get_typed::<CacheControl>().unwrap_or(CacheControl::new());
+		if entry.is_acceptable(now, &request_cc) {
+			return Ok(entry.to_reply(now, CacheStatus::Hit));
+		}
+		if entry.typed::<ETag>().is_some() || entry.typed::<LastModified>().is_some() {
+			return self.revalidate(&key, request, entry);
+		}
+		return match self.fetch(&key, request) {
+			Err(_) if entry.can_serve_stale() => Ok(entry.to_reply(SystemTime::now(), CacheStatus::Stale)),
+			r => r
+		};
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+	use std::io::Cursor;
+	use super::*;
+	use super::super::date::HttpDate;
+
+	fn time(secs: u64) -> SystemTime {
+		return UNIX_EPOCH + Duration::from_secs(1000000000 + secs);
+	}
+
+	fn date(secs: u64) -> String {
+		return HttpDate::from(time(secs)).to_string();
+	}
+
+	fn reply(code: u32, properties: &[(&str, &str)]) -> BufferedReply {
+		let header = properties.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect();
+		return HttpReply::from_parts("HTTP/1.1", code, "Status", header, b"content".to_vec());
+	}
+
+	fn entry(code: u32, properties: &[(&str, &str)]) -> CacheEntry {
+		return CacheEntry::new(&HttpRequest::new(Method::GET, "/"), &reply(code, properties), time(0), time(0));
+	}
+
+	fn secs(s: u64) -> Duration {
+		return Duration::from_secs(s);
+	}
+
+	#[test]
+	fn freshness_lifetime() {
+		let (now, earlier) = (date(0), date(1000));
+		assert_eq!(entry(200, &[("Cache-Control", "max-age=60"), ("Expires", &earlier)]).freshness_lifetime(), secs(60));
+		assert_eq!(entry(200, &[("Date", &now), ("Expires", &earlier)]).freshness_lifetime(), secs(1000));
+		assert_eq!(entry(200, &[("Date", &earlier), ("Expires", &now)]).freshness_lifetime(), secs(0));
+		assert_eq!(entry(200, &[("Expires", "0"), ("Last-Modified", &date(0))]).freshness_lifetime(), secs(0));
+		let last_modified = HttpDate::from(time(0) - secs(1000)).to_string();
+		assert_eq!(entry(200, &[("Date", &now), ("Last-Modified", &last_modified)]).freshness_lifetime(), secs(100));
+		assert_eq!(entry(302, &[("Date", &now), ("Last-Modified", &last_modified)]).freshness_lifetime(), secs(0));
+		assert_eq!(entry(302, &[("Date", &now), ("Last-Modified", &last_modified), ("Cache-Control", "public")]).freshness_lifetime(), secs(100));
+	}
+
+	#[test]
+	fn age() {
+		let e = CacheEntry::new(&HttpRequest::new(Method::GET, "/"), &reply(200, &[("Age", "30")]), time(0), time(2));
+		assert_eq!(e.age(time(2)), secs(32));
+		assert_eq!(e.age(time(12)), secs(42));
+		// The server clock is late, so the apparent age is larger
+		let e = CacheEntry::new(&HttpRequest::new(Method::GET, "/"), &reply(200, &[("Date", &HttpDate::from(time(0) - secs(100)).to_string())]), time(0), time(0));
+		assert_eq!(e.age(time(0)), secs(100));
+		assert_eq!(entry(200, &[("Age", "99999999999999999")]).age(time(0)), secs(MAX_AGE_VALUE));
+		assert_eq!(entry(200, &[("Age", "invalid")]).age(time(0)), secs(0));
+	}
+
+	#[test]
+	fn request_directives() {
+		let e = entry(200, &[("Cache-Control", "max-age=60")]);
+		let none = CacheControl::new();
+		assert!(e.is_acceptable(time(59), &none));
+		assert!(!e.is_acceptable(time(60), &none));
+		assert!(!e.is_acceptable(time(0), &CacheControl::new().with("no-cache", None)));
+		assert!(!e.is_acceptable(time(31), &CacheControl::new().with("max-age", Some("30"))));
+		assert!(e.is_acceptable(time(29), &CacheControl::new().with("min-fresh", Some("30"))));
+		assert!(!e.is_acceptable(time(30), &CacheControl::new().with("min-fresh", Some("30"))));
+		assert!(e.is_acceptable(time(1000), &CacheControl::new().with("max-stale", None)));
+		assert!(e.is_acceptable(time(70), &CacheControl::new().with("max-stale", Some("10"))));
+		assert!(!e.is_acceptable(time(71), &CacheControl::new().with("max-stale", Some("10"))));
+		let e = entry(200, &[("Cache-Control", "max-age=60, must-revalidate")]);
+		assert!(!e.is_acceptable(time(61), &CacheControl::new().with("max-stale", None)));
+		assert!(!e.can_serve_stale());
+		let e = entry(200, &[("Cache-Control", "max-age=60, no-cache")]);
+		assert!(!e.is_acceptable(time(0), &none));
+	}
+
+	#[test]
+	fn entries_are_read_back() {
+		let request = HttpRequest::new(Method::GET, "/").header("Accept", "text/html");
+		let e = CacheEntry::new(&request, &reply(200, &[("Vary", "Accept, Accept-Language"), ("ETag", "\"x\"")]), time(0), time(1));
+		let mut data = Vec::new();
+		e.write_to(&mut data).unwrap();
+		let read = CacheEntry::read_from(&mut Cursor::new(data)).unwrap();
+		assert_eq!((read.code, read.request_time, read.response_time), (200, time(0), time(1)));
+		assert_eq!(read.header, e.header);
+		assert_eq!(read.vary, vec![("accept".to_string(), Some("text/html".to_string())), ("accept-language".to_string(), None)]);
+		assert_eq!(read.body, b"content");
+		assert!(read.matches(&request));
+		assert!(!read.matches(&HttpRequest::new(Method::GET, "/")));
+		for data in &[&b""[..], b"1 2\nHTTP/1.1 200 OK\nH Name: value\n", b"1 2\nHTTP/1.1 200 OK\nX\n\n", b"1\nHTTP/1.1 200 OK\n\n"] {
+			assert!(CacheEntry::read_from(&mut Cursor::new(data.to_vec())).is_err());
+		}
+	}
+
+	/// A client answering with replies given in advance, and recording the requests
+	struct Script {
+		replies: VecDeque<BufferedReply>,
+		requests: Vec<HttpRequest>
+	}
+
+	impl Execute for Script {
+		fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+			self.requests.push(request.clone());
+			return self.replies.pop_front().ok_or(Error::new(ErrorKind::ConnectionRefused, "No more replies"));
+		}
+	}
+
+	fn cache(replies: Vec<BufferedReply>) -> CachingClient<Script, MemoryCache> {
+		return CachingClient::new(Script{replies: replies.into_iter().collect(), requests: Vec::new()}, MemoryCache::new());
+	}
+
+	#[test]
+	fn caching_client() {
+		let get = HttpRequest::new(Method::GET, "/");
+		let mut c = cache(vec![reply(200, &[("Cache-Control", "max-age=60")])]);
+		assert_eq!(c.execute(&get).unwrap().get_cache_status(), Some(CacheStatus::Miss));
+		let hit = c.execute(&get).unwrap();
+		assert_eq!((hit.get_cache_status(), hit.get_body()), (Some(CacheStatus::Hit), &b"content"[..]));
+		assert_eq!(c.get_ref().requests.len(), 1);
+
+		let mut c = cache(vec![reply(200, &[("Cache-Control", "max-age=0"), ("ETag", "\"v1\"")]), reply(304, &[("X-Updated", "yes")])]);
+		c.execute(&get).unwrap();
+		let revalidated = c.execute(&get).unwrap();
+		assert_eq!(revalidated.get_cache_status(), Some(CacheStatus::Revalidated));
+		assert_eq!(revalidated.get_property(&"X-Updated".to_string()), Some(&"yes".to_string()));
+		assert_eq!(c.get_ref().requests[1].get_property(&properties::IF_NONE_MATCH.to_string()), Some(&"\"v1\"".to_string()));
+		// The server cannot be reached anymore
+		assert_eq!(c.execute(&get).unwrap().get_cache_status(), Some(CacheStatus::Stale));
+
+		let mut c = cache(vec![reply(200, &[("Cache-Control", "max-age=60")]), reply(200, &[]), reply(200, &[("Cache-Control", "no-store")])]);
+		c.execute(&get).unwrap();
+		c.execute(&HttpRequest::new(Method::POST, "/")).unwrap();
+		assert_eq!(c.execute(&get).unwrap().get_cache_status(), Some(CacheStatus::Miss));
+		assert!(c.execute(&get).is_err());
+	}
+}
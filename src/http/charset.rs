@@ -0,0 +1,87 @@
+//! Text decoding according to character sets
+//!
+//! Without the "charset" feature, only UTF-8, UTF-16LE, UTF-16BE, US-ASCII and ISO-8859-1 are supported.
+//! With the "charset" feature, all the encodings defined by the WHATWG Encoding Standard are supported.
+use std::io::{Error, ErrorKind};
+
+#[cfg(feature="charset")]
+use encoding_rs::Encoding;
+
+/// Decode `data` into a `String`, according to the `charset` label, or UTF-8 if none is given.
+///
+/// A byte order mark at the beginning of `data` takes precedence over `charset`, and is removed.
+/// If `lossy` is true, malformed sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`, otherwise
+/// they make the decoding fail.
+/// # Example
+/// ```
+/// use http::charset;
+///
+/// assert_eq!(charset::decode(b"caf\xe9", Some("ISO-8859-1"), false).unwrap(), "café");
+/// assert!(charset::decode(b"caf\xe9", None, false).is_err());
+/// assert_eq!(charset::decode(b"caf\xe9", None, true).unwrap(), "caf\u{FFFD}");
+/// ```
+pub fn decode(data: &[u8], charset: Option<&str>, lossy: bool) -> Result<String, Error> {
+	let (label, data) = if data.starts_with(b"\xEF\xBB\xBF") {
+		("utf-8", &data[3..])
+	} else if data.starts_with(b"\xFF\xFE") {
+		("utf-16le", &data[2..])
+	} else if data.starts_with(b"\xFE\xFF") {
+		("utf-16be", &data[2..])
+	} else {
+		(charset.unwrap_or("utf-8"), data)
+	};
+	return decode_label(data, &label.trim().to_ascii_lowercase(), lossy);
+}
+
+#[cfg(feature="charset")]
+fn decode_label(data: &[u8], label: &str, lossy: bool) -> Result<String, Error> {
+	let encoding = match Encoding::for_label(label.as_bytes()) {
+		Some(e) => e,
+		None => return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported charset : {}", label)))
+	};
+	if lossy {
+		return Ok(encoding.decode_without_bom_handling(data).0.into_owned());
+	}
+	return match encoding.decode_without_bom_handling_and_without_replacement(data) {
+		Some(s) => Ok(s.into_owned()),
+		None => Err(Error::new(ErrorKind::InvalidData, format!("Cannot decode content as {}", encoding.name())))
+	};
+}
+
+#[cfg(not(feature="charset"))]
+fn decode_label(data: &[u8], label: &str, lossy: bool) -> Result<String, Error> {
+	return match label {
+		"utf-8" | "utf8" | "unicode-1-1-utf-8" => {
+			if lossy {
+				return Ok(String::from_utf8_lossy(data).into_owned());
+			}
+			return match String::from_utf8(data.to_vec()) {
+				Ok(s) => Ok(s),
+				Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Cannot convert content to utf8 string : {}", e)))
+			};
+		},
+		"utf-16le" | "utf-16" => decode_utf16(data, false, lossy),
+		"utf-16be" => decode_utf16(data, true, lossy),
+		"us-ascii" | "ascii" | "iso-8859-1" | "iso8859-1" | "latin1" | "l1" => Ok(data.iter().map(|&b| b as char).collect()),
+		_ => Err(Error::new(ErrorKind::InvalidData, format!("Unsupported charset : {}", label)))
+	};
+}
+
+#[cfg(not(feature="charset"))]
+fn decode_utf16(data: &[u8], big_endian: bool, lossy: bool) -> Result<String, Error> {
+	if data.len() % 2 != 0 && !lossy {
+		return Err(Error::new(ErrorKind::InvalidData, "Truncated UTF-16 content"));
+	}
+	let units: Vec<u16> = data.chunks(2).map(|c| match (c.len(), big_endian) {
+		(2, true) => (c[0] as u16) << 8 | c[1] as u16,
+		(2, false) => (c[1] as u16) << 8 | c[0] as u16,
+		_ => 0xFFFD
+	}).collect();
+	if lossy {
+		return Ok(String::from_utf16_lossy(&units));
+	}
+	return match String::from_utf16(&units) {
+		Ok(s) => Ok(s),
+		Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Cannot convert content to utf16 string : {}", e)))
+	};
+}
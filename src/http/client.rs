@@ -1,49 +1,246 @@
 //! Module containing HTTP client implementations
-use std::net::{SocketAddr, ToSocketAddrs};
 use std::io::prelude::*;
 use std::collections::HashMap;
 use std::collections::hash_map::{Iter, Keys};
 use std::io::{Error, BufWriter, BufReader, ErrorKind};
+use std::time::Instant;
+use std::sync::Arc;
 
 use super::methods::Method;
-use super::messages::HttpReply;
+use super::messages::{HttpReply, BufferedReply};
+use super::request::HttpRequest;
 use super::constants::properties;
 use super::streams::*;
+use super::encoding::{self, Coding};
+use super::body::{EncodedWriter, ChunkedWriter};
+use super::multipart::Multipart;
+use super::form::Form;
+use super::headers::Header;
+use super::limit::{RateLimiter, Permit};
+use super::breaker::CircuitBreaker;
+use super::trace;
+use super::timing::Timings;
+use super::metrics::{self, Recorder, ConnectionMetrics};
+use super::resolve::{Authority, ToAuthority, Resolver, SystemResolver};
 
 /// Trait for object capable of sending HttpRequests
 pub trait HttpSend {
-	/// Start a new request and return a `BufWriter` to the underlying stream
-	/// so you can write the request body.
+	/// Start sending `request` and return a `BufWriter` to the underlying stream
+	/// so you can write the request body. The body possibly held by `request` is not written.
 	///
 	/// When done, don't forget to call `flush()` on the `BufWriter` in order to flush all the buffer
-	fn send_stream(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>) -> Result<BufWriter<&mut Write>, Error>;
+	fn send_request_stream(&mut self, request: &HttpRequest) -> Result<BufWriter<&mut Write>, Error>;
 	
 	/// Get the reply from stream. Must be called only after a request has been sent
 	fn get_reply(&mut self) -> Result<HttpReply<&mut Read>, Error>;
 	
+	/// Get the reply from stream, entirely read in memory. Must be called only after a request has been sent
+	///
+	/// Unlike `get_reply`, the returned reply doesn't borrow the client.
+	fn get_buffered_reply(&mut self) -> Result<BufferedReply, Error> {
+		return try!(self.get_reply()).into_buffered();
+	}
+	
+	/// Get the host and port requests are sent to, if known. Returns `None` by default
+	fn get_authority(&self) -> Option<Authority> {
+		return None;
+	}
+	
+	/// Start a new request and return a `BufWriter` to the underlying stream
+	/// so you can write the request body.
+	///
+	/// When done, don't forget to call `flush()` on the `BufWriter` in order to flush all the buffer
+	fn send_stream(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>) -> Result<BufWriter<&mut Write>, Error> {
+		return self.send_request_stream(&new_request(method, path, header));
+	}
+	
+	/// Start a new request whose body is encoded on the fly with `coding` (either gzip or zstd), and
+	/// return an `EncodedWriter` so you can write the request body.
+	///
+	/// The body is sent with chunked transfer coding. When done, don't forget to call `finish()` on the `EncodedWriter`
+	/// # Example
+	/// ```no_run
+	/// use std::io::Write;
+	/// use http::client::*;
+	/// use http::methods::Method;
+	/// use http::encoding::Coding;
+	///
+	/// let mut client = HttpClient::new("www.example.com:80").unwrap();
+	/// {
+	/// 	let mut writer = client.send_stream_encoded(Method::POST, "/upload", None, Coding::Gzip).unwrap();
+	/// 	writer.write_all(b"some large content").unwrap();
+	/// 	writer.finish().unwrap();
+	/// }
+	/// let reply = client.get_reply().unwrap();
+	/// # drop(reply);
+	/// ```
+	fn send_stream_encoded(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>, coding: Coding) -> Result<EncodedWriter<BufWriter<&mut Write>>, Error> {
+		// Fail before anything is written, rather than leaving a partial request on the connection
+		if !coding.can_encode() {
+			return Err(Error::new(ErrorKind::InvalidInput, format!("Unsupported content coding for encoding : {}", coding)));
+		}
+		let mut request = new_request(method, path, header);
+		for key in [properties::CONTENT_ENCODING, properties::CONTENT_LENGTH, properties::TRANSFER_ENCODING].iter() {
+			remove_property(&mut request, key);
+		}
+		request = request.header(properties::CONTENT_ENCODING, coding.as_slice())
+			.header(properties::TRANSFER_ENCODING, "chunked");
+		let writer = try!(self.send_request_stream(&request));
+		return EncodedWriter::new(writer, coding);
+	}
+	
+	/// Send a full request, including its body, and return the `HttpReply`.
+	fn send_request(&mut self, request: &HttpRequest) -> Result<HttpReply<&mut Read>, Error> {
+		{
+			let mut writer = try!(self.send_request_stream(request));
+			if let Some(d) = request.get_body() {
+				try!(writer.write_all(d));
+			}
+			try!(writer.flush());
+		}
+		return self.get_reply();
+	}
+	
+	/// Send a full request, including its body, and return the reply entirely read in memory
+	fn send_buffered(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+		return try!(self.send_request(request)).into_buffered();
+	}
+	
 	/// Send a full request and return the `HttpReply`.
 	///
 	/// If some `data` are provided, they are written to the request body, and the corresponding
 	/// `Content-Lenth` header is inserted/updated into request's properties
 	fn send(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>, data: Option<&[u8]>) -> Result<HttpReply<&mut Read>, Error> {
+		let mut request = new_request(method, path, header);
+		if let Some(d) = data {
+			request = request.body(d.to_vec());
+		}
+		return self.send_request(&request);
+	}
+	
+	/// Send a full request with an url-encoded `form` as body, and return the `HttpReply`.
+	///
+	/// The `Content-Type` property is set to `application/x-www-form-urlencoded`
+	/// # Example
+	/// ```no_run
+	/// use http::client::*;
+	/// use http::methods::Method;
+	/// use http::form::Form;
+	///
+	/// let mut client = HttpClient::new("www.example.com:80").unwrap();
+	/// let form = Form::new().add("user", "john").add("comment", "Hello world !");
+	/// let reply = client.send_form(Method::POST, "/comments", None, &form).unwrap();
+	/// # drop(reply);
+	/// ```
+	fn send_form(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>, form: &Form) -> Result<HttpReply<&mut Read>, Error> {
+		return self.send_request(&new_request(method, path, header).form(form));
+	}
+	
+	/// Send a full request with a `multipart/form-data` body, and return the `HttpReply`.
+	///
+	/// The `Content-Type` property is set with the multipart boundary. If the size of every part is known,
+	/// the `Content-Length` is sent, otherwise the body is sent with chunked transfer coding.
+	fn send_multipart(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>, form: Multipart) -> Result<HttpReply<&mut Read>, Error> {
+		let length = form.content_length();
+		let mut request = new_request(method, path, header);
+		for key in [properties::CONTENT_TYPE, properties::CONTENT_LENGTH, properties::TRANSFER_ENCODING].iter() {
+			remove_property(&mut request, key);
+		}
+		request = request.header(properties::CONTENT_TYPE, &form.content_type());
+		request = match length {
+			Some(len) => request.header(properties::CONTENT_LENGTH, &len.to_string()),
+			None => request.header(properties::TRANSFER_ENCODING, "chunked")
+		};
 		{
-			let mut hdr = match header {
-				Some(h) => h.clone(),
-				None => HashMap::new()
-			};
-			if let Some(d) = data {
-				hdr.insert(properties::CONTENT_LENGTH.to_string(), d.len().to_string());
-			}
-			let mut writer = try!(self.send_stream(method, path, Some(&hdr)));
-			if let Some(d) = data {
-				try!(writer.write(d));
+			let mut writer = try!(self.send_request_stream(&request));
+			if length.is_some() {
+				try!(form.write_to(&mut writer));
+				try!(writer.flush());
+			} else {
+				let mut chunked = ChunkedWriter::new(writer);
+				try!(form.write_to(&mut chunked));
+				try!(chunked.finish());
 			}
-			try!(writer.flush());
 		}
 		return self.get_reply();
 	}
+	
+	/// Send a full request whose `data` are encoded with `coding` (either gzip or zstd), and return the `HttpReply`.
+	///
+	/// `data` are encoded in memory, so the `Content-Length` of the encoded body is sent
+	fn send_encoded(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>, data: &[u8], coding: Coding) -> Result<HttpReply<&mut Read>, Error> {
+		let request = try!(new_request(method, path, header).body(data.to_vec()).compress(coding));
+		return self.send_request(&request);
+	}
+}
+
+
+/// Trait for objects executing whole requests, and returning replies entirely read in memory
+///
+/// It is implemented by all the `HttpSend` implementations, including boxed `Http` clients, and by
+/// the layers which can be stacked in front of them, like `cache::CachingClient`.
+pub trait Execute {
+	/// Send `request`, including its body, and return the reply entirely read in memory
+	fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error>;
+	
+	/// Get the host requests are sent to, as `host:port`, if known. Returns `None` by default
+	fn get_host(&self) -> Option<String> {
+		return None;
+	}
+}
+
+impl <H: HttpSend + ?Sized> Execute for H {
+	fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+		return self.send_buffered(request);
+	}
+	
+	fn get_host(&self) -> Option<String> {
+		return self.get_authority().map(|a| a.to_string());
+	}
 }
 
+/// Boxed clients, like the ones returned by `http::open`, send requests with the client they hold
+impl <H: HttpSend + ?Sized> HttpSend for Box<H> {
+	fn send_request_stream(&mut self, request: &HttpRequest) -> Result<BufWriter<&mut Write>, Error> {
+		return (**self).send_request_stream(request);
+	}
+	
+	fn get_reply(&mut self) -> Result<HttpReply<&mut Read>, Error> {
+		return (**self).get_reply();
+	}
+	
+	fn get_buffered_reply(&mut self) -> Result<BufferedReply, Error> {
+		return (**self).get_buffered_reply();
+	}
+	
+	fn get_authority(&self) -> Option<Authority> {
+		return (**self).get_authority();
+	}
+}
+
+/// Build a request from loose `send` arguments
+fn new_request(method: Method, path: &str, header: Option<&HashMap<String, String>>) -> HttpRequest {
+	let mut request = HttpRequest::new(method, path);
+	if let Some(h) = header {
+		for (k, v) in h {
+			request.set_property(k.clone(), v.clone());
+		}
+	}
+	return request;
+}
+
+/// Return true if `header` has the property `key`, ignoring the case of its name
+fn has_property(header: &HashMap<String, String>, key: &str) -> bool {
+	return header.keys().any(|k| k.eq_ignore_ascii_case(key));
+}
+
+/// Remove the property `key` from `request`, whatever the case of its name
+fn remove_property(request: &mut HttpRequest, key: &str) {
+	let names: Vec<String> = request.get_properties_name().filter(|k| k.eq_ignore_ascii_case(key)).cloned().collect();
+	for name in names {
+		request.unset_property(&name);
+	}
+}
 
 /// Represent object with properties. Provides methods for accessing those properties
 pub trait WithHeader {
@@ -61,6 +258,20 @@ pub trait WithHeader {
 	
 	/// Return an iterator over properties from permanent header
 	fn iter(&self) -> Iter<String, String>;
+	
+	/// Get a typed property, ignoring the case of its name. Returns `None` if the property is missing or cannot be parsed
+	fn get_typed<H: Header>(&self) -> Option<H> where Self: Sized {
+		return self.iter().find(|&(k, _)| k.eq_ignore_ascii_case(H::name())).and_then(|(_, v)| H::parse(v).ok());
+	}
+	
+	/// Set a typed property, replacing any previous value
+	fn set_typed<H: Header>(&mut self, value: H) where Self: Sized {
+		let names: Vec<String> = self.get_properties_name().filter(|k| k.eq_ignore_ascii_case(H::name())).cloned().collect();
+		for name in names {
+			self.unset_property(&name);
+		}
+		self.set_property(H::name().to_string(), value.to_string());
+	}
 }
 
 /// Represent and Http object with send capability and properties in header
@@ -68,9 +279,111 @@ pub trait Http: HttpSend+WithHeader{}
 
 /// A simple and low-level HTTP client implementation
 struct BaseClient<S: Stream> {
-	addr: SocketAddr,
+	authority: Authority,
+	resolver: Arc<Resolver>,
 	header: HashMap<String, String>,
-	stream: Option<S>
+	stream: Option<TimedStream<S>>,
+	method: Method,
+	decompress: bool,
+	timeouts: Timeouts,
+	guards: Guards,
+	span: trace::Span
+}
+
+/// Rate limiter and circuit breaker consulted before each request, and metrics recorder, with the current request
+struct Guards {
+	limiter: Option<RateLimiter>,
+	breaker: Option<CircuitBreaker>,
+	metrics: Option<Arc<Recorder>>,
+	method: Method,
+	host: String,
+	permit: Option<Permit>,
+	pending: bool
+}
+
+impl Guards {
+	/// Wait until a request to `host` is allowed by the rate limiter and the circuit breaker
+	fn before(&mut self, method: Method, host: String) -> Result<(), Error> {
+		// The reply of the previous request has not been read, so it cannot be counted as a success
+		self.record(false);
+		self.permit = None;
+		self.method = method;
+		self.host = host;
+		// The circuit breaker is checked first, so rejected requests don't take a rate limiter permit
+		if let Some(ref breaker) = self.breaker {
+			try!(breaker.allow(&self.host));
+			self.pending = true;
+		}
+		let permit = match self.limiter {
+			Some(ref limiter) => limiter.acquire(&self.host).map(Some),
+			None => Ok(None)
+		};
+		match permit {
+			Ok(p) => self.permit = p,
+			Err(e) => {
+				if let Some(ref breaker) = self.breaker {
+					breaker.cancel(&self.host);
+				}
+				self.pending = false;
+				return Err(e);
+			}
+		}
+		return Ok(());
+	}
+
+	/// Record the outcome of the current request in the circuit breaker
+	fn record(&mut self, success: bool) {
+		if self.pending {
+			self.pending = false;
+			if let Some(ref breaker) = self.breaker {
+				breaker.record(&self.host, success);
+			}
+		}
+	}
+
+	/// Account for the current request failing before its reply could be read
+	fn fail(&mut self) {
+		self.count("error");
+		self.record(false);
+		self.permit = None;
+	}
+
+	/// Count the current request in the metrics, with the `status` of its reply
+	fn count(&self, status: &str) {
+		if let Some(ref recorder) = self.metrics {
+			metrics::count_request(&**recorder, self.method, &self.host, status);
+		}
+	}
+
+	/// Account for the reply of the current request, which keeps the rate limiter permit until its content
+	/// has been read. Server errors count as failures
+	fn after<T: Read>(&mut self, reply: &mut Result<HttpReply<T>, Error>) {
+		let permit = self.permit.take();
+		match *reply {
+			Ok(ref mut r) => {
+				if let Some(permit) = permit {
+					r.hold(permit);
+				}
+				self.count(&r.get_code().to_string());
+				self.record(r.get_code() < 500);
+				if let Some(ref limiter) = self.limiter {
+					limiter.update(&self.host, r.iter());
+				}
+			},
+			Err(_) => {
+				self.count("error");
+				self.record(false);
+			}
+		}
+	}
+}
+
+impl Drop for Guards {
+	fn drop(&mut self) {
+		// A request whose reply has not been read counts as a failure, so the circuit breaker doesn't
+		// wait forever for the outcome of a probe
+		self.record(false);
+	}
 }
 
 /// Client for unsecured HTTP
@@ -81,40 +394,145 @@ pub type HttpsClient = BaseClient<HttpsStream>;
 
 impl <S: Stream> BaseClient<S> {
 	
-	/// Create a new HTTP client that will send requests to `addr`.
+	/// Create a new HTTP client that will send requests to `addr`, given as `host:port` or as a socket address.
+	/// The host is resolved each time a connection is opened.
 	/// # Example
 	/// ```no_run
 	/// use http::client::HttpClient;
 	/// let mut client = HttpClient::new("www.google.com:80");
 	/// // Send some requests
 	/// ```
-	pub fn new<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
-		let address = option!(try!(addr.to_socket_addrs()).next(), "Cannot resolve address");
+	pub fn new<A: ToAuthority>(addr: A) -> Result<Self, Error> {
 		let client = BaseClient{
-			addr: address,
+			authority: try!(addr.to_authority()),
+			resolver: Arc::new(SystemResolver),
 			header: HashMap::new(),
 			stream: None,
+			method: Method::GET,
+			decompress: true,
+			timeouts: Timeouts::new(),
+			guards: Guards{limiter: None, breaker: None, metrics: None, method: Method::GET, host: String::new(), permit: None, pending: false},
+			span: trace::Span::none()
 		};
 		return Ok(client);
 	}
 	
-	fn update_properties(&self, header: Option<&HashMap<String, String>>) -> HashMap<String, String> {
-		let mut hdr = match header {
-			Some(h) => h.clone(),
-			None => HashMap::new()
-		};
+	/// Build the header actually sent with `request`, completed with the client permanent properties
+	fn update_properties(&self, request: &HttpRequest) -> HashMap<String, String> {
+		let mut hdr: HashMap<String, String> = request.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
 		for (k, v) in &self.header {
-			if ! hdr.contains_key(k) {
+			if ! has_property(&hdr, k) {
 				hdr.insert(k.clone(), v.clone());
 			}
 		}
+		if self.decompress && ! has_property(&hdr, properties::ACCEPT_ENCODING) {
+			if let Some(codings) = encoding::accept_encoding() {
+				hdr.insert(properties::ACCEPT_ENCODING.to_string(), codings);
+			}
+		}
+		if ! has_property(&hdr, properties::HOST) {
+			hdr.insert(properties::HOST.to_string(), self.authority.to_string());
+		}
 		return hdr;
 	}
 	
-	/// Open an connection to remote host
-	fn connect(&mut self) -> Result<&mut S, Error> {
-		self.stream = Some(try!(S::open(self.addr)));
-		return Ok(self.stream.as_mut().unwrap());
+	/// Enable or disable transparent decompression of replies content. Enabled by default.
+	///
+	/// When enabled, supported content codings are advertised with an `Accept-Encoding` property,
+	/// unless the request already sets one.
+	pub fn set_decompression(&mut self, enabled: bool) {
+		self.decompress = enabled;
+	}
+	
+	/// Set the resolver used to get the addresses of the host each time a connection is opened.
+	/// The resolution is not bound by the connection timeout
+	pub fn set_resolver<R: Resolver + 'static>(&mut self, resolver: R) {
+		self.resolver = Arc::new(resolver);
+	}
+	
+	/// Set the timeouts applied to connections and requests
+	///
+	/// Expired timeouts make requests fail with a `TimedOut` error. The total timeout, or the
+	/// request's own timeout if it has one, covers the connection, the request and the whole reply.
+	pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+		self.timeouts = timeouts;
+	}
+	
+	/// Get the timeouts applied to connections and requests
+	pub fn get_timeouts(&self) -> &Timeouts {
+		return &self.timeouts;
+	}
+	
+	/// Make requests wait for the permission of `limiter` before being sent, and adapt its limits to the
+	/// rate limiting properties of the replies. Requests are limited according to their `Host` property,
+	/// and are counted in flight until the content of their reply has been entirely read, or the reply is dropped
+	pub fn set_rate_limiter(&mut self, limiter: RateLimiter) {
+		self.guards.limiter = Some(limiter);
+	}
+	
+	/// Reject requests to hosts whose circuit is open in `breaker`, and record the outcome of requests in it.
+	/// Connection and write errors, invalid replies, `5xx` replies, and requests whose reply is never read
+	/// count as failures
+	pub fn set_circuit_breaker(&mut self, breaker: CircuitBreaker) {
+		self.guards.breaker = Some(breaker);
+	}
+	
+	/// Report the activity of the client to `recorder`. See the `metrics` module for the recorded metrics
+	pub fn set_metrics<R: Recorder + 'static>(&mut self, recorder: R) {
+		self.guards.metrics = Some(Arc::new(recorder));
+	}
+	
+	/// Get the reply from stream, taking the ownership of the connection it is read from.
+	/// Must be called only after a request has been sent
+	///
+	/// The reply content is not buffered. Since the connection is moved into the reply, the client
+	/// is free to send other requests on new connections, and the reply is `Send` as long as the
+	/// stream is.
+	/// # Example
+	/// ```no_run
+	/// use std::thread;
+	/// use std::io::Write;
+	/// use http::client::*;
+	/// use http::methods::Method;
+	///
+	/// let mut client = HttpClient::new("www.google.com:80").unwrap();
+	/// {
+	/// 	let mut writer = client.send_stream(Method::GET, "/", None).unwrap();
+	/// 	writer.flush().unwrap();
+	/// }
+	/// let mut reply = client.take_reply().unwrap();
+	/// let handle = thread::spawn(move || reply.read_all().unwrap());
+	/// # drop(handle);
+	/// ```
+	pub fn take_reply(&mut self) -> Result<HttpReply<TimedStream<S>>, Error> {
+		let stream = match self.stream.take() {
+			Some(s) => s,
+			None => return Err(Error::new(ErrorKind::NotConnected, "Cannot get reply since no stream is opened"))
+		};
+		let span = self.span.clone();
+		let _entered = span.enter();
+		let timings = stream.get_timings().clone();
+		let mut reply = HttpReply::parse_with(self.method, BufReader::new(stream), self.decompress).map(|mut r| {
+			r.set_timings(timings);
+			r
+		});
+		trace::reply(&reply);
+		self.guards.after(&mut reply);
+		return reply;
+	}
+	
+	/// Open an connection to remote host, which must be done before `deadline`
+	fn connect(&mut self, deadline: Option<Instant>) -> Result<(), Error> {
+		self.stream = None;
+		let mut timings = Timings::new();
+		let addrs = try!(self.resolver.resolve(self.authority.get_host(), self.authority.get_port()));
+		timings.mark_resolved();
+		let stream = try!(S::open_timed(&addrs[..], &try!(self.timeouts.until(deadline)), &mut timings));
+		let mut stream = try!(TimedStream::new(stream, &self.timeouts));
+		stream.set_timings(timings);
+		try!(stream.set_deadline(deadline));
+		self.stream = Some(stream);
+		return Ok(());
 	}
 }
 
@@ -141,40 +559,97 @@ impl <S: Stream> WithHeader for BaseClient<S> {
 }
 
 impl <S: Stream> HttpSend for BaseClient<S>	{
-	fn send_stream(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>) -> Result<BufWriter<&mut Write>, Error> {
-		let hdr = self.update_properties(header);
-		let stream: &mut Write = try!(self.connect());
-		let mut w = BufWriter::new(stream);
-		{
-			let mut writer = &mut w;
-			try_all!{
-				writer.write(method.as_bytes());
-				writer.write(b" ");
-				writer.write(path.as_bytes());
-				writer.write(b"\r\n");
-			};
-			
-			//Write header
-			for (k, v) in hdr {
-				try_all!{
-					writer.write(k.as_bytes());
-					writer.write(b": ");
-					writer.write(v.as_bytes());
-					writer.write(b"\r\n");
-				};
+	fn get_authority(&self) -> Option<Authority> {
+		return Some(self.authority.clone());
+	}
+	
+	fn send_request_stream(&mut self, request: &HttpRequest) -> Result<BufWriter<&mut Write>, Error> {
+		let mut head = HttpRequest::new(request.get_method(), request.get_target()).version(request.get_version());
+		let mut host = String::new();
+		for (k, v) in self.update_properties(request) {
+			if k.eq_ignore_ascii_case(properties::HOST) {
+				host = v.clone();
 			}
-			try!(writer.write(b"\r\n"));
+			head.set_property(k, v);
+		}
+		self.method = request.get_method();
+		let span = trace::Span::request(&head, &host);
+		let _entered = span.enter();
+		self.span = span.clone();
+		// The previous connection must be released before waiting for the rate limiter
+		self.stream = None;
+		try!(self.guards.before(self.method, host));
+		let deadline = request.get_timeout().or(self.timeouts.get_total()).map(|t| Instant::now() + t);
+		if let Err(e) = self.connect(deadline) {
+			self.guards.fail();
+			return Err(e);
+		}
+		let stream = self.stream.as_mut().unwrap();
+		if let Some(ref recorder) = self.guards.metrics {
+			let timings = stream.get_timings().clone();
+			stream.observe(ConnectionMetrics::new(recorder.clone(), self.method, &self.guards.host, timings));
 		}
+		let mut w = BufWriter::new(stream as &mut Write);
+		if let Err(e) = head.write_head(&mut w) {
+			self.guards.fail();
+			return Err(e);
+		}
+		trace::request(&head);
 		return Ok(w);
 	}
 	
+	fn send_request(&mut self, request: &HttpRequest) -> Result<HttpReply<&mut Read>, Error> {
+		let written = {
+			let mut writer = try!(self.send_request_stream(request));
+			match request.get_body() {
+				Some(d) => writer.write_all(d),
+				None => Ok(())
+			}.and_then(|_| writer.flush())
+		};
+		if let Err(e) = written {
+			self.guards.fail();
+			return Err(e);
+		}
+		return self.get_reply();
+	}
+	
 	fn get_reply(&mut self) -> Result<HttpReply<&mut Read>, Error> {
-		let stream: &mut Read = match self.stream.as_mut() {
+		let span = self.span.clone();
+		let _entered = span.enter();
+		let stream = match self.stream.as_mut() {
 			Some(s) => s,
 			None => return Err(Error::new(ErrorKind::NotConnected, "Cannot get reply since no stream is opened"))
 		};
-		return HttpReply::parse(BufReader::new(stream));
+		let timings = stream.get_timings().clone();
+		let mut reply = HttpReply::parse_with(self.method, BufReader::new(stream as &mut Read), self.decompress).map(|mut r| {
+			r.set_timings(timings);
+			r
+		});
+		trace::reply(&reply);
+		self.guards.after(&mut reply);
+		return reply;
 	}
 }
 
-impl <S: Stream> Http for BaseClient<S>{}
\ No newline at end of file
+impl <S: Stream> Http for BaseClient<S>{}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use super::*;
+	use super::super::breaker::CircuitOpen;
+	use super::super::limit::Limit;
+
+	#[test]
+	fn open_circuit_does_not_take_a_permit() {
+		let limiter = RateLimiter::new().global(Limit::new().rate(0.001, 1).max_in_flight(1)).max_wait(Duration::from_millis(0));
+		let breaker = CircuitBreaker::new().consecutive_failures(1);
+		breaker.record("127.0.0.1:9", false);
+		let mut client = HttpClient::new("127.0.0.1:9").unwrap();
+		client.set_rate_limiter(limiter.clone());
+		client.set_circuit_breaker(breaker.clone());
+		let err = client.send(Method::GET, "/", None, None).err().unwrap();
+		assert!(CircuitOpen::from_io(&err).is_some());
+		assert!(limiter.acquire("127.0.0.1:9").is_ok());
+	}
+}
\ No newline at end of file
@@ -7,6 +7,28 @@ pub mod properties {
 	pub const ACCEPT: &'static str = "Accept";
 	pub const DATE: &'static str = "Date";
 	pub const LOCATION: &'static str = "Location";
+	pub const HOST: &'static str = "Host";
+	pub const TRANSFER_ENCODING: &'static str = "Transfer-Encoding";
+	pub const CONTENT_ENCODING: &'static str = "Content-Encoding";
+	pub const ACCEPT_ENCODING: &'static str = "Accept-Encoding";
+	pub const ACCEPT_LANGUAGE: &'static str = "Accept-Language";
+	pub const CONTENT_DISPOSITION: &'static str = "Content-Disposition";
+	pub const CACHE_CONTROL: &'static str = "Cache-Control";
+	pub const ETAG: &'static str = "ETag";
+	pub const LAST_MODIFIED: &'static str = "Last-Modified";
+	pub const EXPIRES: &'static str = "Expires";
+	pub const IF_MODIFIED_SINCE: &'static str = "If-Modified-Since";
+	pub const IF_NONE_MATCH: &'static str = "If-None-Match";
+	pub const IF_MATCH: &'static str = "If-Match";
+	pub const IF_RANGE: &'static str = "If-Range";
+	pub const AGE: &'static str = "Age";
+	pub const RANGE: &'static str = "Range";
+	pub const CONTENT_RANGE: &'static str = "Content-Range";
+	pub const AUTHORIZATION: &'static str = "Authorization";
+	pub const LINK: &'static str = "Link";
+	pub const RETRY_AFTER: &'static str = "Retry-After";
+	pub const VARY: &'static str = "Vary";
+	pub const ALLOW: &'static str = "Allow";
 }
 
 /// Mime types constants
@@ -15,4 +37,6 @@ pub mod mimetypes {
 	pub const APP_JSON: &'static str = "application/json";
 	pub const APP_XML: &'static str = "application/xml";
 	pub const APP_OCTET_STREAM: &'static str = "application/octet-stream";
+	pub const MULTIPART_FORM_DATA: &'static str = "multipart/form-data";
+	pub const APP_FORM_URLENCODED: &'static str = "application/x-www-form-urlencoded";
 }
@@ -0,0 +1,210 @@
+//! HTTP-date parsing and formatting
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::str::FromStr;
+use std::fmt;
+
+const DAYS: [&'static str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&'static str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// A date, with a one second precision, as used in `Date`, `Expires` or `Last-Modified` properties
+///
+/// Dates are parsed from IMF-fixdate, and obsolete RFC 850 and asctime formats. They are always
+/// formatted as IMF-fixdate.
+/// # Example
+/// ```
+/// use http::date::HttpDate;
+///
+/// let date = HttpDate::parse("Sunday, 06-Nov-94 08:49:37 GMT").unwrap();
+/// assert_eq!(date, HttpDate::parse("Sun Nov  6 08:49:37 1994").unwrap());
+/// assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate {
+	/// Seconds since the UNIX epoch
+	secs: i64
+}
+
+impl HttpDate {
+	/// Get the current date
+	pub fn now() -> HttpDate {
+		return HttpDate::from(SystemTime::now());
+	}
+
+	/// Parse a date in IMF-fixdate, RFC 850 or asctime format
+	pub fn parse(s: &str) -> Result<HttpDate, Error> {
+		let invalid = || Error::new(ErrorKind::InvalidData, format!("Cannot parse HTTP date : {}", s));
+		let (day, month, year, time) = match s.find(',') {
+			Some(i) => {
+				let parts: Vec<&str> = s[i+1..].split_whitespace().collect();
+				if parts.len() == 5 && parts[4] == "GMT" {
+					// IMF-fixdate : Sun, 06 Nov 1994 08:49:37 GMT
+					(parts[0], parts[1], try!(number(parts[2]).ok_or_else(&invalid)), parts[3])
+				} else if parts.len() == 3 && parts[2] == "GMT" {
+					// RFC 850 : Sunday, 06-Nov-94 08:49:37 GMT
+					let date: Vec<&str> = parts[0].split('-').collect();
+					if date.len() != 3 {
+						return Err(invalid());
+					}
+					let year = try!(number(date[2]).ok_or_else(&invalid));
+					(date[0], date[1], if year < 70 { year + 2000 } else if year < 100 { year + 1900 } else { year }, parts[1])
+				} else {
+					return Err(invalid());
+				}
+			},
+			None => {
+				// asctime : Sun Nov  6 08:49:37 1994
+				let parts: Vec<&str> = s.split_whitespace().collect();
+				if parts.len() != 5 {
+					return Err(invalid());
+				}
+				(parts[2], parts[1], try!(number(parts[4]).ok_or_else(&invalid)), parts[3])
+			}
+		};
+		let day = try!(number(day).ok_or_else(&invalid));
+		let month = try!(MONTHS.iter().position(|m| *m == month).ok_or_else(&invalid)) as i64 + 1;
+		let time: Vec<i64> = try!(time.split(':').map(number).collect::<Option<Vec<i64>>>().ok_or_else(&invalid));
+		// Years are written with four digits, which also keeps the number of seconds from overflowing
+		if year > 9999 || time.len() != 3 || time[0] > 23 || time[1] > 59 || time[2] > 60 || day < 1 || day > days_in_month(year, month) {
+			return Err(invalid());
+		}
+		let secs = days_from_civil(year, month, day) * 86400 + time[0] * 3600 + time[1] * 60 + time[2];
+		return Ok(HttpDate{secs: secs});
+	}
+
+	/// Get the duration elapsed from `earlier` to this date, or zero if `earlier` is later
+	pub fn duration_since(&self, earlier: HttpDate) -> Duration {
+		if self.secs <= earlier.secs {
+			return Duration::from_secs(0);
+		}
+		return Duration::from_secs((self.secs - earlier.secs) as u64);
+	}
+}
+
+fn number(s: &str) -> Option<i64> {
+	if s.is_empty() || !s.bytes().all(|b| b >= b'0' && b <= b'9') {
+		return None;
+	}
+	return i64::from_str(s).ok();
+}
+
+fn is_leap(year: i64) -> bool {
+	return year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+	return match month {
+		2 if is_leap(year) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31
+	};
+}
+
+/// Number of days since 1970-01-01 of a proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let y = if month <= 2 { year - 1 } else { year };
+	let era = if y >= 0 { y } else { y - 399 } / 400;
+	let yoe = y - era * 400;
+	let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	return era * 146097 + doe - 719468;
+}
+
+/// Proleptic gregorian date, as `(year, month, day)`, of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+	let z = days + 719468;
+	let era = if z >= 0 { z } else { z - 146096 } / 146097;
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	return (yoe + era * 400 + if month <= 2 { 1 } else { 0 }, month, day);
+}
+
+impl From<SystemTime> for HttpDate {
+	fn from(time: SystemTime) -> HttpDate {
+		return match time.duration_since(UNIX_EPOCH) {
+			Ok(d) => HttpDate{secs: d.as_secs() as i64},
+			Err(e) => HttpDate{secs: -(e.duration().as_secs() as i64)}
+		};
+	}
+}
+
+impl From<HttpDate> for SystemTime {
+	fn from(date: HttpDate) -> SystemTime {
+		if date.secs >= 0 {
+			return UNIX_EPOCH + Duration::from_secs(date.secs as u64);
+		}
+		return UNIX_EPOCH - Duration::from_secs((-date.secs) as u64);
+	}
+}
+
+impl FromStr for HttpDate {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<HttpDate, Error> {
+		return HttpDate::parse(s);
+	}
+}
+
+impl fmt::Display for HttpDate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let days = if self.secs >= 0 { self.secs / 86400 } else { (self.secs - 86399) / 86400 };
+		let secs = self.secs - days * 86400;
+		let (year, month, day) = civil_from_days(days);
+		let weekday = ((days % 7 + 11) % 7) as usize;
+		return write!(f, "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+			DAYS[weekday], day, MONTHS[(month - 1) as usize], year, secs / 3600, secs % 3600 / 60, secs % 60);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn time_fields_must_all_be_numbers() {
+		assert!(HttpDate::parse("Sun, 06 Nov 1994 08:xx:49:37 GMT").is_err());
+		assert!(HttpDate::parse("Sun, 06 Nov 1994 08:49: GMT").is_err());
+		assert!(HttpDate::parse("Sun, 06 Nov 1994 08:49:37:00 GMT").is_err());
+		assert!(HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT").is_ok());
+	}
+
+	#[test]
+	fn all_formats_give_the_same_date() {
+		let date = HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+		assert_eq!(HttpDate::parse("Sunday, 06-Nov-94 08:49:37 GMT").unwrap(), date);
+		assert_eq!(HttpDate::parse("Sun Nov  6 08:49:37 1994").unwrap(), date);
+		assert_eq!(SystemTime::from(date), UNIX_EPOCH + Duration::from_secs(784111777));
+		assert_eq!(HttpDate::from(SystemTime::from(date)), date);
+	}
+
+	#[test]
+	fn two_digit_years_and_calendar() {
+		assert_eq!(HttpDate::parse("Thursday, 01-Jan-70 00:00:00 GMT").unwrap().to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+		assert_eq!(HttpDate::parse("Monday, 01-Jan-69 00:00:00 GMT").unwrap().to_string(), "Tue, 01 Jan 2069 00:00:00 GMT");
+		assert!(HttpDate::parse("Thu, 29 Feb 2024 12:00:00 GMT").is_ok());
+		assert!(HttpDate::parse("Sat, 29 Feb 2025 12:00:00 GMT").is_err());
+		assert!(HttpDate::parse("Tue, 29 Feb 2000 12:00:00 GMT").is_ok());
+		assert!(HttpDate::parse("Sun, 29 Feb 1900 12:00:00 GMT").is_err());
+		assert_eq!(HttpDate::parse("Wed, 31 Dec 1969 23:59:59 GMT").unwrap().to_string(), "Wed, 31 Dec 1969 23:59:59 GMT");
+	}
+
+	#[test]
+	fn invalid_dates() {
+		for value in &["", "Sun, 06 Nov 1994 08:49:37", "Sun, 06 Foo 1994 08:49:37 GMT", "Sun, 32 Oct 1994 08:49:37 GMT",
+			"Sun, 06 Nov 1994 24:00:00 GMT", "Sun, 06 Nov 10000 08:49:37 GMT", "Sun, -6 Nov 1994 08:49:37 GMT", "Sun Nov 6 08:49:37"] {
+			assert!(HttpDate::parse(value).is_err(), "{} should not parse", value);
+		}
+	}
+
+	#[test]
+	fn duration_between_dates() {
+		let earlier = HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+		let later = HttpDate::parse("Sun, 06 Nov 1994 08:50:07 GMT").unwrap();
+		assert_eq!(later.duration_since(earlier), Duration::from_secs(30));
+		assert_eq!(earlier.duration_since(later), Duration::from_secs(0));
+	}
+}
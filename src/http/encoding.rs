@@ -0,0 +1,324 @@
+//! Content codings support
+//!
+//! Each content coding is enabled by its own cargo feature : "gzip", "deflate", "brotli" and "zstd"
+use std::io::{Error, ErrorKind, Read, Write};
+use std::fmt;
+
+#[cfg(feature="gzip")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature="deflate")]
+use std::io::{Chain, Cursor};
+#[cfg(feature="deflate")]
+use flate2::read::{ZlibDecoder, DeflateDecoder};
+#[cfg(feature="brotli")]
+use brotli_decompressor::Decompressor as BrotliDecoder;
+#[cfg(feature="zstd")]
+use std::io::BufReader;
+#[cfg(feature="zstd")]
+use zstd::stream::read::Decoder as ZstdDecoder;
+#[cfg(feature="gzip")]
+use flate2::write::GzEncoder;
+#[cfg(feature="gzip")]
+use flate2::Compression;
+#[cfg(feature="zstd")]
+use zstd::stream::write::Encoder as ZstdEncoder;
+
+/// HTTP content codings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Coding {
+	Identity,
+	Gzip,
+	Deflate,
+	Brotli,
+	Zstd
+}
+
+impl Coding {
+	/// Parse a content coding name. Returns `None` if the coding is unknown
+	pub fn parse(name: &str) -> Option<Coding> {
+		let name = name.trim().to_ascii_lowercase();
+		return match &name[..] {
+			"identity" => Some(Coding::Identity),
+			"gzip" | "x-gzip" => Some(Coding::Gzip),
+			"deflate" => Some(Coding::Deflate),
+			"br" => Some(Coding::Brotli),
+			"zstd" => Some(Coding::Zstd),
+			_ => None
+		};
+	}
+
+	/// Parse a comma separated list of codings, like the value of a `Content-Encoding` header.
+	/// Returns `None` if one of the codings is unknown
+	pub fn parse_list(list: &str) -> Option<Vec<Coding>> {
+		let mut codings = Vec::new();
+		for name in list.split(',').filter(|s| !s.trim().is_empty()) {
+			match Coding::parse(name) {
+				Some(Coding::Identity) => {},
+				Some(c) => codings.push(c),
+				None => return None
+			}
+		}
+		return Some(codings);
+	}
+
+	/// Return a static string representation of the coding name
+	pub fn as_slice(&self) -> &'static str {
+		return match *self {
+			Coding::Identity => "identity",
+			Coding::Gzip => "gzip",
+			Coding::Deflate => "deflate",
+			Coding::Brotli => "br",
+			Coding::Zstd => "zstd"
+		};
+	}
+
+	/// Return true if the crate has been built with support for decoding this coding
+	pub fn is_supported(&self) -> bool {
+		return match *self {
+			Coding::Identity => true,
+			Coding::Gzip => cfg!(feature="gzip"),
+			Coding::Deflate => cfg!(feature="deflate"),
+			Coding::Brotli => cfg!(feature="brotli"),
+			Coding::Zstd => cfg!(feature="zstd")
+		};
+	}
+
+	/// Return true if the crate has been built with support for encoding request bodies with this coding
+	pub fn can_encode(&self) -> bool {
+		return match *self {
+			Coding::Identity => true,
+			Coding::Gzip => cfg!(feature="gzip"),
+			Coding::Zstd => cfg!(feature="zstd"),
+			Coding::Deflate | Coding::Brotli => false
+		};
+	}
+}
+
+impl fmt::Display for Coding {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return f.write_str(self.as_slice());
+	}
+}
+
+/// Build the value of an `Accept-Encoding` header listing all the supported codings.
+/// Returns `None` if the crate has been built without any of them
+pub fn accept_encoding() -> Option<String> {
+	let codings: Vec<&str> = [Coding::Gzip, Coding::Deflate, Coding::Brotli, Coding::Zstd].iter()
+		.filter(|c| c.is_supported())
+		.map(|c| c.as_slice())
+		.collect();
+	if codings.is_empty() {
+		return None;
+	}
+	return Some(codings.join(", "));
+}
+
+/// A reader decoding a content, possibly encoded with several stacked codings
+pub enum Decoder<R: Read> {
+	Identity(R),
+	#[cfg(feature="gzip")]
+	Gzip(Box<MultiGzDecoder<Decoder<R>>>),
+	#[cfg(feature="deflate")]
+	Deflate(Box<Inflater<Decoder<R>>>),
+	#[cfg(feature="brotli")]
+	Brotli(Box<BrotliDecoder<Decoder<R>>>),
+	#[cfg(feature="zstd")]
+	Zstd(Box<ZstdDecoder<'static, BufReader<Decoder<R>>>>)
+}
+
+impl <R: Read> Decoder<R> {
+	/// Create a decoder for a content read from `reader`, which has been encoded with
+	/// `codings`, in the order they are given (ie: the order of the `Content-Encoding` header)
+	/// # Example
+	/// ```
+	/// use std::io::Read;
+	/// use http::encoding::{Coding, Decoder};
+	///
+	/// let mut decoder = Decoder::new(&b"raw data"[..], &[Coding::Identity]).unwrap();
+	/// let mut s = String::new();
+	/// decoder.read_to_string(&mut s).unwrap();
+	/// assert_eq!(s, "raw data");
+	/// ```
+	pub fn new(reader: R, codings: &[Coding]) -> Result<Decoder<R>, Error> {
+		let mut decoder = Decoder::Identity(reader);
+		for coding in codings.iter().rev() {
+			decoder = match *coding {
+				Coding::Identity => decoder,
+				#[cfg(feature="gzip")]
+				Coding::Gzip => Decoder::Gzip(Box::new(MultiGzDecoder::new(decoder))),
+				#[cfg(feature="deflate")]
+				Coding::Deflate => Decoder::Deflate(Box::new(Inflater::new(decoder))),
+				#[cfg(feature="brotli")]
+				Coding::Brotli => Decoder::Brotli(Box::new(BrotliDecoder::new(decoder, 4096))),
+				#[cfg(feature="zstd")]
+				Coding::Zstd => Decoder::Zstd(Box::new(try!(ZstdDecoder::new(decoder)))),
+				#[allow(unreachable_patterns)]
+				c => return Err(Error::new(ErrorKind::Other, format!("Unsupported content coding : {}", c)))
+			};
+		}
+		return Ok(decoder);
+	}
+}
+
+impl <R: Read> Read for Decoder<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		return match *self {
+			Decoder::Identity(ref mut r) => r.read(buf),
+			#[cfg(feature="gzip")]
+			Decoder::Gzip(ref mut r) => r.read(buf),
+			#[cfg(feature="deflate")]
+			Decoder::Deflate(ref mut r) => r.read(buf),
+			#[cfg(feature="brotli")]
+			Decoder::Brotli(ref mut r) => r.read(buf),
+			#[cfg(feature="zstd")]
+			Decoder::Zstd(ref mut r) => r.read(buf)
+		};
+	}
+}
+
+#[cfg(feature="deflate")]
+enum Inflate<R: Read> {
+	Start(Option<R>),
+	Empty,
+	Zlib(ZlibDecoder<Chain<Cursor<Vec<u8>>, R>>),
+	Raw(DeflateDecoder<Chain<Cursor<Vec<u8>>, R>>)
+}
+
+/// A reader decoding the deflate coding. The content should be in zlib format, but many servers send
+/// raw deflate data instead, which is decoded when the content doesn't start with a zlib header. An empty content is left empty
+#[cfg(feature="deflate")]
+pub struct Inflater<R: Read> {
+	state: Inflate<R>
+}
+
+#[cfg(feature="deflate")]
+impl <R: Read> Inflater<R> {
+	/// Create a new reader decoding the deflate content read from `reader`
+	pub fn new(reader: R) -> Inflater<R> {
+		return Inflater{state: Inflate::Start(Some(reader))};
+	}
+}
+
+#[cfg(feature="deflate")]
+impl <R: Read> Read for Inflater<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		if let Inflate::Start(ref mut reader) = self.state {
+			let mut r = match reader.take() {
+				Some(r) => r,
+				None => return Err(Error::new(ErrorKind::Other, "Deflate decoder failed to start"))
+			};
+			let mut head = [0u8; 2];
+			let mut len = 0;
+			while len < head.len() {
+				match try!(r.read(&mut head[len..])) {
+					0 => break,
+					n => len += n
+				}
+			}
+			// The zlib header : deflate method in the low bits of the first byte, and a check on both bytes
+			let zlib = len == 2 && head[0] & 0x0f == 8 && (head[0] as u16 * 256 + head[1] as u16) % 31 == 0;
+			let input = Cursor::new(head[..len].to_vec()).chain(r);
+			self.state = if len == 0 {
+				Inflate::Empty
+			} else if zlib {
+				Inflate::Zlib(ZlibDecoder::new(input))
+			} else {
+				Inflate::Raw(DeflateDecoder::new(input))
+			};
+		}
+		return match self.state {
+			Inflate::Zlib(ref mut r) => r.read(buf),
+			Inflate::Raw(ref mut r) => r.read(buf),
+			Inflate::Start(_) | Inflate::Empty => Ok(0)
+		};
+	}
+}
+
+/// A writer encoding content with a single coding. Only gzip and zstd codings can be used for encoding
+pub enum Encoder<W: Write> {
+	Identity(W),
+	#[cfg(feature="gzip")]
+	Gzip(GzEncoder<W>),
+	#[cfg(feature="zstd")]
+	Zstd(ZstdEncoder<'static, W>)
+}
+
+impl <W: Write> Encoder<W> {
+	/// Create a new encoder writing `coding` encoded content to `writer`
+	pub fn new(writer: W, coding: Coding) -> Result<Encoder<W>, Error> {
+		return match coding {
+			Coding::Identity => Ok(Encoder::Identity(writer)),
+			#[cfg(feature="gzip")]
+			Coding::Gzip => Ok(Encoder::Gzip(GzEncoder::new(writer, Compression::default()))),
+			#[cfg(feature="zstd")]
+			Coding::Zstd => Ok(Encoder::Zstd(try!(ZstdEncoder::new(writer, 0)))),
+			#[allow(unreachable_patterns)]
+			c => Err(Error::new(ErrorKind::Other, format!("Unsupported content coding for encoding : {}", c)))
+		};
+	}
+
+	/// Write the end of the encoded content and return the underlying writer
+	pub fn finish(self) -> Result<W, Error> {
+		return match self {
+			Encoder::Identity(w) => Ok(w),
+			#[cfg(feature="gzip")]
+			Encoder::Gzip(w) => w.finish(),
+			#[cfg(feature="zstd")]
+			Encoder::Zstd(w) => w.finish()
+		};
+	}
+}
+
+impl <W: Write> Write for Encoder<W> {
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		return match *self {
+			Encoder::Identity(ref mut w) => w.write(buf),
+			#[cfg(feature="gzip")]
+			Encoder::Gzip(ref mut w) => w.write(buf),
+			#[cfg(feature="zstd")]
+			Encoder::Zstd(ref mut w) => w.write(buf)
+		};
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		return match *self {
+			Encoder::Identity(ref mut w) => w.flush(),
+			#[cfg(feature="gzip")]
+			Encoder::Gzip(ref mut w) => w.flush(),
+			#[cfg(feature="zstd")]
+			Encoder::Zstd(ref mut w) => w.flush()
+		};
+	}
+}
+
+/// Encode `data` with `coding` in memory
+pub fn encode(data: &[u8], coding: Coding) -> Result<Vec<u8>, Error> {
+	let mut encoder = try!(Encoder::new(Vec::with_capacity(data.len() / 2), coding));
+	try!(encoder.write_all(data));
+	return encoder.finish();
+}
+
+#[cfg(all(test, feature="deflate"))]
+mod tests {
+	use std::io::Read;
+	use super::*;
+
+	#[test]
+	fn deflate_accepts_zlib_and_raw_content() {
+		use std::io::Write;
+		use flate2::Compression;
+		use flate2::write::{ZlibEncoder, DeflateEncoder};
+		let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+		zlib.write_all(b"hello world").unwrap();
+		let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+		raw.write_all(b"hello world").unwrap();
+		for data in &[zlib.finish().unwrap(), raw.finish().unwrap()] {
+			let mut s = String::new();
+			Decoder::new(&data[..], &[Coding::Deflate]).unwrap().read_to_string(&mut s).unwrap();
+			assert_eq!(s, "hello world");
+		}
+		let mut s = String::new();
+		Decoder::new(&b""[..], &[Coding::Deflate]).unwrap().read_to_string(&mut s).unwrap();
+		assert_eq!(s, "");
+	}
+}
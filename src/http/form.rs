@@ -0,0 +1,147 @@
+//! `application/x-www-form-urlencoded` encoding and decoding
+use std::fmt;
+use std::slice::Iter;
+
+/// An ordered list of form fields, which can be encoded as a form body or a query string
+/// # Example
+/// ```
+/// use http::form::Form;
+///
+/// let form = Form::new().add("name", "John Doe").add("lang", "fr&en");
+/// assert_eq!(form.encode(), "name=John+Doe&lang=fr%26en");
+///
+/// let decoded = Form::parse("name=John+Doe&lang=fr%26en");
+/// assert_eq!(decoded.get("lang"), Some("fr&en"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Form {
+	pairs: Vec<(String, String)>
+}
+
+impl Form {
+	/// Create a new empty form
+	pub fn new() -> Form {
+		return Form{pairs: Vec::new()};
+	}
+
+	/// Decode a form body or a query string, without the leading `?`.
+	/// Invalid percent-encoded sequences are kept as is, and invalid UTF-8 is replaced
+	pub fn parse(encoded: &str) -> Form {
+		let mut form = Form::new();
+		for pair in encoded.split('&').filter(|s| !s.is_empty()) {
+			let mut splt = pair.splitn(2, '=');
+			let key = decode_component(splt.next().unwrap_or(""));
+			let value = decode_component(splt.next().unwrap_or(""));
+			form.pairs.push((key, value));
+		}
+		return form;
+	}
+
+	/// Decode the query string from a request target, like `"/search?q=rust"`
+	pub fn from_target(target: &str) -> Form {
+		let query = match target.find('?') {
+			Some(i) => &target[i+1..],
+			None => ""
+		};
+		let query = match query.find('#') {
+			Some(i) => &query[..i],
+			None => query
+		};
+		return Form::parse(query);
+	}
+
+	/// Append a field to the form
+	pub fn add(mut self, key: &str, value: &str) -> Form {
+		self.pairs.push((key.to_string(), value.to_string()));
+		return self;
+	}
+
+	/// Get the value of the first field named `key`
+	pub fn get(&self, key: &str) -> Option<&str> {
+		return self.pairs.iter().find(|p| p.0 == key).map(|p| &p.1[..]);
+	}
+
+	/// Get the values of all the fields named `key`, in order
+	pub fn get_all(&self, key: &str) -> Vec<&str> {
+		return self.pairs.iter().filter(|p| p.0 == key).map(|p| &p.1[..]).collect();
+	}
+
+	/// Return an iterator over the fields, in order
+	pub fn iter(&self) -> Iter<(String, String)> {
+		return self.pairs.iter();
+	}
+
+	/// Get the number of fields
+	pub fn len(&self) -> usize {
+		return self.pairs.len();
+	}
+
+	/// Return true if the form has no field
+	pub fn is_empty(&self) -> bool {
+		return self.pairs.is_empty();
+	}
+
+	/// Encode the form fields
+	pub fn encode(&self) -> String {
+		let encoded: Vec<String> = self.pairs.iter()
+			.map(|&(ref k, ref v)| format!("{}={}", encode_component(k), encode_component(v)))
+			.collect();
+		return encoded.join("&");
+	}
+}
+
+impl fmt::Display for Form {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return f.write_str(&self.encode());
+	}
+}
+
+/// Encode a form field name or value. Spaces are encoded as `+`
+pub fn encode_component(s: &str) -> String {
+	return encode(s, true);
+}
+
+/// Percent-encode every byte of `s` which is not an unreserved URI character. Spaces are encoded as `%20`
+pub fn percent_encode(s: &str) -> String {
+	return encode(s, false);
+}
+
+fn encode(s: &str, plus: bool) -> String {
+	let mut out = String::with_capacity(s.len());
+	for b in s.bytes() {
+		match b {
+			b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' => out.push(b as char),
+			b' ' if plus => out.push('+'),
+			_ => out.push_str(&format!("%{:02X}", b))
+		}
+	}
+	return out;
+}
+
+/// Decode a form field name or value. `+` are decoded as spaces
+pub fn decode_component(s: &str) -> String {
+	let bytes = s.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'+' => out.push(b' '),
+			b'%' if i + 2 < bytes.len() && hex(bytes[i+1]).is_some() && hex(bytes[i+2]).is_some() => {
+				out.push(hex(bytes[i+1]).unwrap() * 16 + hex(bytes[i+2]).unwrap());
+				i += 2;
+			},
+			b => out.push(b)
+		}
+		i += 1;
+	}
+	return String::from_utf8_lossy(&out).into_owned();
+}
+
+fn hex(b: u8) -> Option<u8> {
+	return match b {
+		b'0'...b'9' => Some(b - b'0'),
+		b'a'...b'f' => Some(b - b'a' + 10),
+		b'A'...b'F' => Some(b - b'A' + 10),
+		_ => None
+	};
+}
@@ -0,0 +1,825 @@
+//! Typed headers
+//!
+//! Each type of this module represents the value of a property, and can be parsed from and formatted to
+//! its textual representation. Typed properties can be read and written with `get_typed` and `set_typed`
+//! methods of both `WithHeader` implementations and `HttpReply`.
+//! # Example
+//! ```
+//! use http::methods::Method;
+//! use http::request::HttpRequest;
+//! use http::client::WithHeader;
+//! use http::headers::{Range, ByteRange, CacheControl};
+//!
+//! let mut req = HttpRequest::new(Method::GET, "/file");
+//! req.set_typed(Range::bytes(vec![ByteRange::FromTo(0, 499), ByteRange::Last(100)]));
+//! assert_eq!(req.get_property(&"Range".to_string()).unwrap(), "bytes=0-499,-100");
+//!
+//! req.set_property("cache-control".to_string(), "max-age=60, no-transform".to_string());
+//! let cc: CacheControl = req.get_typed().unwrap();
+//! assert_eq!(cc.max_age().unwrap().as_secs(), 60);
+//! ```
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+use std::time::Duration;
+use std::fmt;
+
+use super::methods::Method;
+use super::mime::MediaType;
+use super::date::HttpDate;
+use super::constants::properties;
+use super::syntax::{Parser, is_token, split_list};
+
+/// A typed property value
+pub trait Header: fmt::Display + Sized {
+	/// Get the name of the property
+	fn name() -> &'static str;
+
+	/// Parse a property value
+	fn parse(value: &str) -> Result<Self, Error>;
+}
+
+fn invalid(name: &str, value: &str) -> Error {
+	return Error::new(ErrorKind::InvalidData, format!("Cannot parse {} property : {}", name, value));
+}
+
+fn parse_u64(name: &str, value: &str) -> Result<u64, Error> {
+	return u64::from_str(value.trim()).map_err(|_| invalid(name, value));
+}
+
+/// Write `value` as a token if possible, or as a quoted string otherwise
+fn write_value(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+	if !value.is_empty() && value.bytes().all(is_token) {
+		return f.write_str(value);
+	}
+	return write!(f, "\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
+/// `Content-Length` property
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentLength(pub u64);
+
+impl Header for ContentLength {
+	fn name() -> &'static str {
+		return properties::CONTENT_LENGTH;
+	}
+
+	fn parse(value: &str) -> Result<ContentLength, Error> {
+		return Ok(ContentLength(try!(parse_u64(ContentLength::name(), value))));
+	}
+}
+
+impl fmt::Display for ContentLength {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return write!(f, "{}", self.0);
+	}
+}
+
+/// `Content-Type` property
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentType(pub MediaType);
+
+impl Header for ContentType {
+	fn name() -> &'static str {
+		return properties::CONTENT_TYPE;
+	}
+
+	fn parse(value: &str) -> Result<ContentType, Error> {
+		return Ok(ContentType(try!(MediaType::parse(value))));
+	}
+}
+
+impl fmt::Display for ContentType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return self.0.fmt(f);
+	}
+}
+
+/// `Cache-Control` property, made of directives with optional arguments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheControl {
+	directives: Vec<(String, Option<String>)>
+}
+
+impl CacheControl {
+	/// Create an empty `Cache-Control` property
+	pub fn new() -> CacheControl {
+		return CacheControl{directives: Vec::new()};
+	}
+
+	/// Append a directive, with an optional argument
+	pub fn with(mut self, directive: &str, argument: Option<&str>) -> CacheControl {
+		self.directives.push((directive.to_ascii_lowercase(), argument.map(|a| a.to_string())));
+		return self;
+	}
+
+	/// Return true if `directive` is present
+	pub fn has(&self, directive: &str) -> bool {
+		return self.directives.iter().any(|d| d.0.eq_ignore_ascii_case(directive));
+	}
+
+	/// Get the argument of `directive`, if it is present with an argument
+	pub fn get(&self, directive: &str) -> Option<&str> {
+		return self.directives.iter()
+			.find(|d| d.0.eq_ignore_ascii_case(directive))
+			.and_then(|d| d.1.as_ref().map(|a| &a[..]));
+	}
+
+	fn seconds(&self, directive: &str) -> Option<Duration> {
+		return self.get(directive).and_then(|a| u64::from_str(a).ok()).map(Duration::from_secs);
+	}
+
+	/// Get the `max-age` directive
+	pub fn max_age(&self) -> Option<Duration> {
+		return self.seconds("max-age");
+	}
+
+	/// Get the `s-maxage` directive
+	pub fn s_maxage(&self) -> Option<Duration> {
+		return self.seconds("s-maxage");
+	}
+
+	/// Get the `max-stale` directive
+	pub fn max_stale(&self) -> Option<Duration> {
+		return self.seconds("max-stale");
+	}
+
+	/// Get the `min-fresh` directive
+	pub fn min_fresh(&self) -> Option<Duration> {
+		return self.seconds("min-fresh");
+	}
+
+	/// Return true if the `no-cache` directive is present
+	pub fn no_cache(&self) -> bool {
+		return self.has("no-cache");
+	}
+
+	/// Return true if the `no-store` directive is present
+	pub fn no_store(&self) -> bool {
+		return self.has("no-store");
+	}
+
+	/// Return true if the `must-revalidate` directive is present
+	pub fn must_revalidate(&self) -> bool {
+		return self.has("must-revalidate");
+	}
+
+	/// Return true if the `private` directive is present
+	pub fn is_private(&self) -> bool {
+		return self.has("private");
+	}
+
+	/// Return true if the `public` directive is present
+	pub fn is_public(&self) -> bool {
+		return self.has("public");
+	}
+}
+
+impl Header for CacheControl {
+	fn name() -> &'static str {
+		return properties::CACHE_CONTROL;
+	}
+
+	fn parse(value: &str) -> Result<CacheControl, Error> {
+		let mut cc = CacheControl::new();
+		for item in split_list(value) {
+			let mut parser = Parser::new(item);
+			let directive = parser.token();
+			let argument = if parser.eat(b'=') { Some(try!(parser.value())) } else { None };
+			if directive.is_empty() || !parser.at_end() {
+				return Err(invalid(CacheControl::name(), value));
+			}
+			cc.directives.push((directive.to_ascii_lowercase(), argument));
+		}
+		return Ok(cc);
+	}
+}
+
+impl fmt::Display for CacheControl {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, &(ref directive, ref argument)) in self.directives.iter().enumerate() {
+			if i > 0 {
+				try!(f.write_str(", "));
+			}
+			try!(f.write_str(directive));
+			if let Some(ref a) = *argument {
+				try!(f.write_str("="));
+				try!(write_value(f, a));
+			}
+		}
+		return Ok(());
+	}
+}
+
+/// `ETag` property, an entity tag which may be weak
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ETag {
+	tag: String,
+	weak: bool
+}
+
+impl ETag {
+	/// Create a new entity tag. `tag` must not contain the surrounding quotes
+	pub fn new(tag: &str, weak: bool) -> ETag {
+		return ETag{tag: tag.to_string(), weak: weak};
+	}
+
+	/// Get the opaque tag, without quotes
+	pub fn get_tag(&self) -> &str {
+		return &self.tag;
+	}
+
+	/// Return true if this is a weak entity tag
+	pub fn is_weak(&self) -> bool {
+		return self.weak;
+	}
+
+	/// Strong comparison : both tags must be strong and identical
+	pub fn strong_eq(&self, other: &ETag) -> bool {
+		return !self.weak && !other.weak && self.tag == other.tag;
+	}
+
+	/// Weak comparison : tags must be identical, whether they are weak or not
+	pub fn weak_eq(&self, other: &ETag) -> bool {
+		return self.tag == other.tag;
+	}
+}
+
+impl Header for ETag {
+	fn name() -> &'static str {
+		return properties::ETAG;
+	}
+
+	fn parse(value: &str) -> Result<ETag, Error> {
+		let v = value.trim();
+		let (weak, v) = if v.starts_with("W/") { (true, &v[2..]) } else { (false, v) };
+		if v.len() < 2 || !v.starts_with('"') || !v.ends_with('"') {
+			return Err(invalid(ETag::name(), value));
+		}
+		return Ok(ETag::new(&v[1..v.len()-1], weak));
+	}
+}
+
+impl fmt::Display for ETag {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return write!(f, "{}\"{}\"", if self.weak {"W/"} else {""}, self.tag);
+	}
+}
+
+macro_rules! date_header {
+	($(#[$doc:meta])* $name:ident, $property:expr) => (
+		$(#[$doc])*
+		#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+		pub struct $name(pub HttpDate);
+
+		impl Header for $name {
+			fn name() -> &'static str {
+				return $property;
+			}
+
+			fn parse(value: &str) -> Result<$name, Error> {
+				return Ok($name(try!(HttpDate::parse(value.trim()))));
+			}
+		}
+
+		impl fmt::Display for $name {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				return self.0.fmt(f);
+			}
+		}
+	)
+}
+
+date_header!(
+	/// `Date` property, the date the message has been generated
+	Date, properties::DATE);
+date_header!(
+	/// `Expires` property
+	Expires, properties::EXPIRES);
+date_header!(
+	/// `Last-Modified` property
+	LastModified, properties::LAST_MODIFIED);
+date_header!(
+	/// `If-Modified-Since` property
+	IfModifiedSince, properties::IF_MODIFIED_SINCE);
+
+/// A range of bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+	/// From the first to the last position, both included
+	FromTo(u64, u64),
+	/// From a position to the end
+	From(u64),
+	/// The given number of last bytes
+	Last(u64)
+}
+
+impl ByteRange {
+	/// Get the first and last positions, both included, of this range in a content of `length` bytes.
+	/// Returns `None` if the range is not satisfiable
+	pub fn resolve(&self, length: u64) -> Option<(u64, u64)> {
+		return match *self {
+			ByteRange::FromTo(first, last) if first <= last && first < length => Some((first, if last < length {last} else {length - 1})),
+			ByteRange::From(first) if first < length => Some((first, length - 1)),
+			ByteRange::Last(n) if n > 0 && length > 0 => Some((if n < length {length - n} else {0}, length - 1)),
+			_ => None
+		};
+	}
+}
+
+impl fmt::Display for ByteRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match *self {
+			ByteRange::FromTo(first, last) => write!(f, "{}-{}", first, last),
+			ByteRange::From(first) => write!(f, "{}-", first),
+			ByteRange::Last(n) => write!(f, "-{}", n)
+		};
+	}
+}
+
+/// `Range` property, in bytes unit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Range {
+	ranges: Vec<ByteRange>
+}
+
+impl Range {
+	/// Create a `Range` property requesting the given byte ranges
+	pub fn bytes(ranges: Vec<ByteRange>) -> Range {
+		return Range{ranges: ranges};
+	}
+
+	/// Get the requested ranges
+	pub fn get_ranges(&self) -> &[ByteRange] {
+		return &self.ranges;
+	}
+}
+
+impl Header for Range {
+	fn name() -> &'static str {
+		return properties::RANGE;
+	}
+
+	fn parse(value: &str) -> Result<Range, Error> {
+		let v = value.trim();
+		if !v.starts_with("bytes=") {
+			return Err(invalid(Range::name(), value));
+		}
+		let mut ranges = Vec::new();
+		for spec in v[6..].split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+			let mut splt = spec.splitn(2, '-');
+			let first = splt.next().unwrap_or("").trim();
+			let last = match splt.next() {
+				Some(l) => l.trim(),
+				None => return Err(invalid(Range::name(), value))
+			};
+			ranges.push(match (first.is_empty(), last.is_empty()) {
+				(false, false) => ByteRange::FromTo(try!(parse_u64(Range::name(), first)), try!(parse_u64(Range::name(), last))),
+				(false, true) => ByteRange::From(try!(parse_u64(Range::name(), first))),
+				(true, false) => ByteRange::Last(try!(parse_u64(Range::name(), last))),
+				(true, true) => return Err(invalid(Range::name(), value))
+			});
+		}
+		if ranges.is_empty() {
+			return Err(invalid(Range::name(), value));
+		}
+		return Ok(Range::bytes(ranges));
+	}
+}
+
+impl fmt::Display for Range {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(f.write_str("bytes="));
+		for (i, r) in self.ranges.iter().enumerate() {
+			if i > 0 {
+				try!(f.write_str(","));
+			}
+			try!(r.fmt(f));
+		}
+		return Ok(());
+	}
+}
+
+/// `Content-Range` property, in bytes unit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+	range: Option<(u64, u64)>,
+	complete_length: Option<u64>
+}
+
+impl ContentRange {
+	/// Create a `Content-Range` property for the bytes from `first` to `last`, both included, of a content
+	/// whose complete length may be unknown
+	pub fn new(first: u64, last: u64, complete_length: Option<u64>) -> ContentRange {
+		return ContentRange{range: Some((first, last)), complete_length: complete_length};
+	}
+
+	/// Create a `Content-Range` property for an unsatisfied range request, as sent with `416 Range Not Satisfiable` replies
+	pub fn unsatisfied(complete_length: u64) -> ContentRange {
+		return ContentRange{range: None, complete_length: Some(complete_length)};
+	}
+
+	/// Get the first and last positions, both included, of the enclosed range
+	pub fn get_range(&self) -> Option<(u64, u64)> {
+		return self.range;
+	}
+
+	/// Get the complete length of the content, if known
+	pub fn get_complete_length(&self) -> Option<u64> {
+		return self.complete_length;
+	}
+}
+
+impl Header for ContentRange {
+	fn name() -> &'static str {
+		return properties::CONTENT_RANGE;
+	}
+
+	fn parse(value: &str) -> Result<ContentRange, Error> {
+		let v = value.trim();
+		if !v.starts_with("bytes ") {
+			return Err(invalid(ContentRange::name(), value));
+		}
+		let mut splt = v[6..].splitn(2, '/');
+		let range = splt.next().unwrap_or("").trim();
+		let complete = match splt.next() {
+			Some(c) => c.trim(),
+			None => return Err(invalid(ContentRange::name(), value))
+		};
+		let complete_length = if complete == "*" { None } else { Some(try!(parse_u64(ContentRange::name(), complete))) };
+		if range == "*" {
+			return match complete_length {
+				Some(l) => Ok(ContentRange::unsatisfied(l)),
+				None => Err(invalid(ContentRange::name(), value))
+			};
+		}
+		let mut splt = range.splitn(2, '-');
+		let first = try!(parse_u64(ContentRange::name(), splt.next().unwrap_or("")));
+		let last = try!(parse_u64(ContentRange::name(), splt.next().unwrap_or("")));
+		if last < first || complete_length.map(|l| last >= l).unwrap_or(false) {
+			return Err(invalid(ContentRange::name(), value));
+		}
+		return Ok(ContentRange::new(first, last, complete_length));
+	}
+}
+
+impl fmt::Display for ContentRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(match self.range {
+			Some((first, last)) => write!(f, "bytes {}-{}/", first, last),
+			None => f.write_str("bytes */")
+		});
+		return match self.complete_length {
+			Some(l) => write!(f, "{}", l),
+			None => f.write_str("*")
+		};
+	}
+}
+
+/// `If-Range` property, the validator a range request is conditioned on
+///
+/// Only strong entity tags can be used, since ranges of different representations must never be combined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IfRange {
+	/// The range is sent if the representation still has this strong entity tag
+	ETag(ETag),
+	/// The range is sent if the representation has not been modified since this date
+	Date(HttpDate)
+}
+
+impl Header for IfRange {
+	fn name() -> &'static str {
+		return properties::IF_RANGE;
+	}
+
+	fn parse(value: &str) -> Result<IfRange, Error> {
+		let v = value.trim();
+		if v.starts_with('"') {
+			return Ok(IfRange::ETag(try!(ETag::parse(v))));
+		}
+		if v.starts_with("W/") {
+			return Err(invalid(IfRange::name(), value));
+		}
+		return Ok(IfRange::Date(try!(HttpDate::parse(v))));
+	}
+}
+
+impl fmt::Display for IfRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match *self {
+			IfRange::ETag(ref tag) => tag.fmt(f),
+			IfRange::Date(ref date) => date.fmt(f)
+		};
+	}
+}
+
+/// `Authorization` property, made of a scheme and credentials
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Authorization {
+	scheme: String,
+	credentials: String
+}
+
+impl Authorization {
+	/// Create an `Authorization` property with the given `scheme` and `credentials`
+	pub fn new(scheme: &str, credentials: &str) -> Authorization {
+		return Authorization{scheme: scheme.to_string(), credentials: credentials.to_string()};
+	}
+
+	/// Create a `Basic` authorization for `user` and `password`
+	pub fn basic(user: &str, password: &str) -> Authorization {
+		return Authorization::new("Basic", &base64(format!("{}:{}", user, password).as_bytes()));
+	}
+
+	/// Create a `Bearer` authorization with `token`
+	pub fn bearer(token: &str) -> Authorization {
+		return Authorization::new("Bearer", token);
+	}
+
+	/// Get the authorization scheme
+	pub fn get_scheme(&self) -> &str {
+		return &self.scheme;
+	}
+
+	/// Get the credentials
+	pub fn get_credentials(&self) -> &str {
+		return &self.credentials;
+	}
+}
+
+impl Header for Authorization {
+	fn name() -> &'static str {
+		return properties::AUTHORIZATION;
+	}
+
+	fn parse(value: &str) -> Result<Authorization, Error> {
+		let mut splt = value.trim().splitn(2, ' ');
+		let scheme = splt.next().unwrap_or("");
+		if scheme.is_empty() || !scheme.bytes().all(is_token) {
+			return Err(invalid(Authorization::name(), value));
+		}
+		return Ok(Authorization::new(scheme, splt.next().unwrap_or("").trim()));
+	}
+}
+
+impl fmt::Display for Authorization {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.credentials.is_empty() {
+			return f.write_str(&self.scheme);
+		}
+		return write!(f, "{} {}", self.scheme, self.credentials);
+	}
+}
+
+fn base64(data: &[u8]) -> String {
+	const CHARS: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+	for chunk in data.chunks(3) {
+		let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	return out;
+}
+
+/// A single link of a `Link` property
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkValue {
+	uri: String,
+	params: Vec<(String, String)>
+}
+
+impl LinkValue {
+	/// Create a new link to `uri`, without parameters
+	pub fn new(uri: &str) -> LinkValue {
+		return LinkValue{uri: uri.to_string(), params: Vec::new()};
+	}
+
+	/// Add a parameter to the link
+	pub fn with_param(mut self, name: &str, value: &str) -> LinkValue {
+		self.params.push((name.to_ascii_lowercase(), value.to_string()));
+		return self;
+	}
+
+	/// Get the target URI
+	pub fn get_uri(&self) -> &str {
+		return &self.uri;
+	}
+
+	/// Get the value of a parameter
+	pub fn get_param(&self, name: &str) -> Option<&str> {
+		return self.params.iter().find(|p| p.0.eq_ignore_ascii_case(name)).map(|p| &p.1[..]);
+	}
+
+	/// Return true if the link has the given relation type, among the space separated ones of its `rel` parameter
+	pub fn has_rel(&self, rel: &str) -> bool {
+		return self.get_param("rel").map(|r| r.split_whitespace().any(|r| r.eq_ignore_ascii_case(rel))).unwrap_or(false);
+	}
+}
+
+impl fmt::Display for LinkValue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(write!(f, "<{}>", self.uri));
+		for &(ref k, ref v) in &self.params {
+			try!(write!(f, "; {}=", k));
+			try!(write_value(f, v));
+		}
+		return Ok(());
+	}
+}
+
+/// `Link` property, as used for pagination
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+	links: Vec<LinkValue>
+}
+
+impl Link {
+	/// Create a `Link` property with the given links
+	pub fn new(links: Vec<LinkValue>) -> Link {
+		return Link{links: links};
+	}
+
+	/// Get all the links
+	pub fn get_links(&self) -> &[LinkValue] {
+		return &self.links;
+	}
+
+	/// Get the first link with the relation type `rel`, like `"next"`
+	pub fn find_rel(&self, rel: &str) -> Option<&LinkValue> {
+		return self.links.iter().find(|l| l.has_rel(rel));
+	}
+}
+
+impl Header for Link {
+	fn name() -> &'static str {
+		return properties::LINK;
+	}
+
+	fn parse(value: &str) -> Result<Link, Error> {
+		let mut parser = Parser::new(value);
+		let mut links = Vec::new();
+		loop {
+			if !parser.eat(b'<') {
+				return Err(invalid(Link::name(), value));
+			}
+			let uri = parser.until(b'>');
+			links.push(LinkValue{uri: uri.trim().to_string(), params: try!(parser.params())});
+			if parser.at_end() {
+				return Ok(Link::new(links));
+			}
+			if !parser.eat(b',') {
+				return Err(invalid(Link::name(), value));
+			}
+		}
+	}
+}
+
+impl fmt::Display for Link {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, l) in self.links.iter().enumerate() {
+			if i > 0 {
+				try!(f.write_str(", "));
+			}
+			try!(l.fmt(f));
+		}
+		return Ok(());
+	}
+}
+
+/// `Retry-After` property
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryAfter {
+	/// A delay, in seconds
+	Delay(u64),
+	/// A date
+	Date(HttpDate)
+}
+
+impl RetryAfter {
+	/// Get the delay to wait from now
+	pub fn delay(&self) -> Duration {
+		return match *self {
+			RetryAfter::Delay(s) => Duration::from_secs(s),
+			RetryAfter::Date(d) => d.duration_since(HttpDate::now())
+		};
+	}
+}
+
+impl Header for RetryAfter {
+	fn name() -> &'static str {
+		return properties::RETRY_AFTER;
+	}
+
+	fn parse(value: &str) -> Result<RetryAfter, Error> {
+		let v = value.trim();
+		if !v.is_empty() && v.bytes().all(|b| b >= b'0' && b <= b'9') {
+			return Ok(RetryAfter::Delay(try!(parse_u64(RetryAfter::name(), v))));
+		}
+		return Ok(RetryAfter::Date(try!(HttpDate::parse(v))));
+	}
+}
+
+impl fmt::Display for RetryAfter {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match *self {
+			RetryAfter::Delay(s) => write!(f, "{}", s),
+			RetryAfter::Date(ref d) => d.fmt(f)
+		};
+	}
+}
+
+/// `Vary` property, listing the request properties a reply depends on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vary(pub Vec<String>);
+
+impl Vary {
+	/// Return true if the reply varies on anything, ie the property is `*`
+	pub fn is_any(&self) -> bool {
+		return self.0.iter().any(|v| v == "*");
+	}
+}
+
+impl Header for Vary {
+	fn name() -> &'static str {
+		return properties::VARY;
+	}
+
+	fn parse(value: &str) -> Result<Vary, Error> {
+		return Ok(Vary(split_list(value).into_iter().map(|s| s.to_string()).collect()));
+	}
+}
+
+impl fmt::Display for Vary {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return f.write_str(&self.0.join(", "));
+	}
+}
+
+/// `Allow` property, listing the methods supported by a resource
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Allow(pub Vec<Method>);
+
+impl Header for Allow {
+	fn name() -> &'static str {
+		return properties::ALLOW;
+	}
+
+	fn parse(value: &str) -> Result<Allow, Error> {
+		let mut methods = Vec::new();
+		for m in split_list(value) {
+			methods.push(try!(Method::from_str(m)));
+		}
+		return Ok(Allow(methods));
+	}
+}
+
+impl fmt::Display for Allow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let methods: Vec<&str> = self.0.iter().map(|m| m.as_slice()).collect();
+		return f.write_str(&methods.join(", "));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn range_parsing() {
+		let range = Range::parse(" bytes=0-99, 200- ,-50").unwrap();
+		assert_eq!(range.get_ranges(), &[ByteRange::FromTo(0, 99), ByteRange::From(200), ByteRange::Last(50)]);
+		assert_eq!(range.to_string(), "bytes=0-99,200-,-50");
+		for value in &["items=0-1", "bytes=", "bytes=-", "bytes=5", "bytes=a-b", "bytes=1--2"] {
+			assert!(Range::parse(value).is_err(), "{} should not parse", value);
+		}
+	}
+
+	#[test]
+	fn byte_range_resolution() {
+		assert_eq!(ByteRange::FromTo(0, 99).resolve(50), Some((0, 49)));
+		assert_eq!(ByteRange::FromTo(10, 5).resolve(50), None);
+		assert_eq!(ByteRange::FromTo(50, 60).resolve(50), None);
+		assert_eq!(ByteRange::From(10).resolve(50), Some((10, 49)));
+		assert_eq!(ByteRange::From(50).resolve(50), None);
+		assert_eq!(ByteRange::Last(10).resolve(50), Some((40, 49)));
+		assert_eq!(ByteRange::Last(100).resolve(50), Some((0, 49)));
+		assert_eq!(ByteRange::Last(0).resolve(50), None);
+		assert_eq!(ByteRange::Last(10).resolve(0), None);
+	}
+
+	#[test]
+	fn content_range_parsing() {
+		assert_eq!(ContentRange::parse("bytes 0-99/1000").unwrap(), ContentRange::new(0, 99, Some(1000)));
+		assert_eq!(ContentRange::parse("bytes 0-99/*").unwrap(), ContentRange::new(0, 99, None));
+		assert_eq!(ContentRange::parse("bytes */1000").unwrap(), ContentRange::unsatisfied(1000));
+		assert_eq!(ContentRange::unsatisfied(1000).to_string(), "bytes */1000");
+		for value in &["bytes */*", "bytes 0-99", "bytes 99-0/1000", "bytes 0-1000/1000", "bytes 0-/1000", "items 0-1/2"] {
+			assert!(ContentRange::parse(value).is_err(), "{} should not parse", value);
+		}
+	}
+}
@@ -0,0 +1,127 @@
+//! JSON request and reply bodies support. Only available if "json" feature is enabled
+use std::io::{self, Read, ErrorKind};
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use super::methods::Method;
+use super::messages::HttpReply;
+use super::request::HttpRequest;
+use super::client::{HttpSend, WithHeader};
+use super::constants::{properties, mimetypes};
+
+/// Errors which can occur when sending or receiving JSON documents
+#[derive(Debug)]
+pub enum JsonError {
+	/// An I/O error occured while sending the request or reading the reply
+	Io(io::Error),
+	/// The reply has a non successful status, with its code and status text
+	Status(u32, String),
+	/// The request body cannot be serialized
+	Encode(serde_json::Error),
+	/// The reply body cannot be deserialized
+	Decode(serde_json::Error)
+}
+
+impl fmt::Display for JsonError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match *self {
+			JsonError::Io(ref e) => write!(f, "I/O error : {}", e),
+			JsonError::Status(code, ref status) => write!(f, "Unexpected HTTP status : {} {}", code, status),
+			JsonError::Encode(ref e) => write!(f, "Cannot serialize JSON body : {}", e),
+			JsonError::Decode(ref e) => write!(f, "Cannot deserialize JSON body : {}", e)
+		};
+	}
+}
+
+impl error::Error for JsonError {
+	fn cause(&self) -> Option<&error::Error> {
+		return match *self {
+			JsonError::Io(ref e) => Some(e),
+			JsonError::Status(_, _) => None,
+			JsonError::Encode(ref e) | JsonError::Decode(ref e) => Some(e)
+		};
+	}
+}
+
+impl From<io::Error> for JsonError {
+	fn from(e: io::Error) -> JsonError {
+		return JsonError::Io(e);
+	}
+}
+
+impl From<JsonError> for io::Error {
+	fn from(e: JsonError) -> io::Error {
+		return match e {
+			JsonError::Io(e) => e,
+			JsonError::Decode(e) => io::Error::new(ErrorKind::InvalidData, e),
+			e => io::Error::new(ErrorKind::Other, e)
+		};
+	}
+}
+
+impl <R: Read> HttpReply<R> {
+	/// Deserialize the reply's JSON content
+	///
+	/// Fails with `JsonError::Status` if the reply status code is not a `2xx` one
+	pub fn json<T: DeserializeOwned>(&mut self) -> Result<T, JsonError> {
+		if self.get_code() < 200 || self.get_code() >= 300 {
+			return Err(JsonError::Status(self.get_code(), self.get_status().clone()));
+		}
+		let reader = try!(self.get_decoded_reader());
+		return serde_json::from_reader(reader).map_err(|e| {
+			if e.is_io() {
+				return JsonError::Io(e.into());
+			}
+			return JsonError::Decode(e);
+		});
+	}
+}
+
+/// Extension trait providing JSON requests to every `HttpSend` implementation
+pub trait SendJson: HttpSend {
+	/// Send a full request with `value` serialized as a JSON body, and return the `HttpReply`.
+	///
+	/// `Content-Type` and `Accept` properties are set to `application/json`, unless they are provided in `header`
+	/// # Example
+	/// ```no_run
+	/// # extern crate http;
+	/// # #[macro_use] extern crate serde_derive;
+	/// use http::client::*;
+	/// use http::methods::Method;
+	/// use http::json::SendJson;
+	///
+	/// #[derive(Serialize, Deserialize)]
+	/// struct User {
+	/// 	name: String
+	/// }
+	///
+	/// # fn main() {
+	/// let mut client = HttpClient::new("www.example.com:80").unwrap();
+	/// let user = User{name: "John".to_string()};
+	/// let created: User = client.send_json(Method::POST, "/users", None, &user).unwrap().json().unwrap();
+	/// # drop(created);
+	/// # }
+	/// ```
+	fn send_json<T: Serialize + ?Sized>(&mut self, method: Method, path: &str, header: Option<&HashMap<String, String>>, value: &T) -> Result<HttpReply<&mut Read>, JsonError> {
+		let data = try!(serde_json::to_vec(value).map_err(JsonError::Encode));
+		let mut request = HttpRequest::new(method, path);
+		if let Some(h) = header {
+			for (k, v) in h {
+				request.set_property(k.clone(), v.clone());
+			}
+		}
+		for key in &[properties::CONTENT_TYPE, properties::ACCEPT] {
+			if request.get_property(&key.to_string()).is_none() {
+				request.set_property(key.to_string(), mimetypes::APP_JSON.to_string());
+			}
+		}
+		return Ok(try!(self.send_request(&request.body(data))));
+	}
+}
+
+impl <H: HttpSend + ?Sized> SendJson for H {}
@@ -0,0 +1,402 @@
+//! Client-side rate limiting and concurrency limiting
+//!
+//! A `RateLimiter` combines token buckets, limiting the rate of requests, with a maximum number of
+//! requests in flight. Limits can be set globally, for every host, or for specific hosts. A limiter is a
+//! cheap handle on a shared state, so clones of it can be given to several clients, possibly used from
+//! several threads, which then share the same limits.
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use http::client::HttpClient;
+//! use http::limit::{RateLimiter, Limit};
+//!
+//! let limiter = RateLimiter::new()
+//! 	.global(Limit::new().max_in_flight(32))
+//! 	.per_host(Limit::new().rate(10.0, 20).max_in_flight(4))
+//! 	.host("api.partner.com:443", Limit::new().rate(1.0, 1))
+//! 	.max_wait(Duration::from_secs(5));
+//! let mut client = HttpClient::new("www.example.com:80").unwrap();
+//! client.set_rate_limiter(limiter.clone());
+//! ```
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, Condvar};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Limits applied to requests
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limit {
+	rate: Option<(f64, u32)>,
+	max_in_flight: Option<usize>
+}
+
+impl Limit {
+	/// Create a new limit, which doesn't limit anything
+	pub fn new() -> Limit {
+		return Limit::default();
+	}
+
+	/// Limit the rate of requests to `per_second` on average, allowing bursts of up to `burst` requests
+	/// # Panics
+	/// Panics if `per_second` is not a positive finite number
+	pub fn rate(mut self, per_second: f64, burst: u32) -> Limit {
+		assert!(per_second > 0.0 && per_second.is_finite(), "Invalid request rate : {}", per_second);
+		self.rate = Some((per_second, if burst > 0 { burst } else { 1 }));
+		return self;
+	}
+
+	/// Limit the number of requests in flight, ie sent and whose reply has not been entirely read
+	pub fn max_in_flight(mut self, max: usize) -> Limit {
+		self.max_in_flight = Some(max);
+		return self;
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Wait {
+	Ready,
+	For(Duration),
+	Release
+}
+
+/// The state of a token bucket, and the requests in flight
+struct Bucket {
+	limit: Limit,
+	tokens: f64,
+	last: Instant,
+	blocked_until: Option<Instant>,
+	in_flight: usize
+}
+
+impl Bucket {
+	fn new(limit: Limit) -> Bucket {
+		let tokens = limit.rate.map(|r| r.1 as f64).unwrap_or(0.0);
+		return Bucket{limit: limit, tokens: tokens, last: Instant::now(), blocked_until: None, in_flight: 0};
+	}
+
+	fn refill(&mut self, now: Instant) {
+		if let Some((rate, burst)) = self.limit.rate {
+			let elapsed = now.duration_since(self.last);
+			self.tokens += rate * (elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9);
+			if self.tokens > burst as f64 {
+				self.tokens = burst as f64;
+			}
+		}
+		self.last = now;
+	}
+
+	fn wait(&mut self, now: Instant) -> Wait {
+		self.refill(now);
+		if let Some(until) = self.blocked_until {
+			if until > now {
+				return Wait::For(until - now);
+			}
+			self.blocked_until = None;
+		}
+		if let Some((rate, _)) = self.limit.rate {
+			if self.tokens < 1.0 {
+				let secs = (1.0 - self.tokens) / rate;
+				return Wait::For(Duration::new(secs as u64, (secs.fract() * 1e9) as u32 + 1));
+			}
+		}
+		if self.limit.max_in_flight.map(|m| self.in_flight >= m).unwrap_or(false) {
+			return Wait::Release;
+		}
+		return Wait::Ready;
+	}
+
+	fn take(&mut self) {
+		if self.limit.rate.is_some() {
+			self.tokens -= 1.0;
+		}
+		self.in_flight += 1;
+	}
+}
+
+struct State {
+	global: Bucket,
+	per_host: Limit,
+	host_limits: HashMap<String, Limit>,
+	hosts: HashMap<String, Bucket>,
+	max_wait: Option<Duration>
+}
+
+impl State {
+	fn host(&mut self, host: &str) -> &mut Bucket {
+		let limit = *self.host_limits.get(host).unwrap_or(&self.per_host);
+		return self.hosts.entry(host.to_string()).or_insert_with(|| Bucket::new(limit));
+	}
+
+	/// Get how long to wait before a request to `host` can be sent
+	fn wait(&mut self, host: &str, now: Instant) -> Wait {
+		let global = self.global.wait(now);
+		return match (global, self.host(host).wait(now)) {
+			(Wait::For(a), Wait::For(b)) => Wait::For(if a > b { a } else { b }),
+			(Wait::For(d), _) | (_, Wait::For(d)) => Wait::For(d),
+			(Wait::Release, _) | (_, Wait::Release) => Wait::Release,
+			(Wait::Ready, Wait::Ready) => Wait::Ready
+		};
+	}
+}
+
+struct Shared {
+	state: Mutex<State>,
+	released: Condvar
+}
+
+/// A rate and concurrency limiter, shared by all its clones
+#[derive(Clone)]
+pub struct RateLimiter {
+	shared: Arc<Shared>
+}
+
+impl RateLimiter {
+	/// Create a new limiter without any limit. Requests wait for as long as needed by default
+	pub fn new() -> RateLimiter {
+		let state = State{global: Bucket::new(Limit::new()), per_host: Limit::new(), host_limits: HashMap::new(), hosts: HashMap::new(), max_wait: None};
+		return RateLimiter{shared: Arc::new(Shared{state: Mutex::new(state), released: Condvar::new()})};
+	}
+
+	fn lock(&self) -> MutexGuard<State> {
+		return match self.shared.state.lock() {
+			Ok(s) => s,
+			Err(e) => e.into_inner()
+		};
+	}
+
+	/// Set the limit applied to all requests together
+	pub fn global(self, limit: Limit) -> RateLimiter {
+		self.lock().global = Bucket::new(limit);
+		return self;
+	}
+
+	/// Set the limit applied to each host which doesn't have a specific one
+	pub fn per_host(self, limit: Limit) -> RateLimiter {
+		{
+			let mut state = self.lock();
+			state.per_host = limit;
+			state.hosts.clear();
+		}
+		return self;
+	}
+
+	/// Set the limit applied to `host`. Hosts are identified by the `Host` property of requests, like `"www.example.com:80"`
+	pub fn host(self, host: &str, limit: Limit) -> RateLimiter {
+		{
+			let mut state = self.lock();
+			state.host_limits.insert(host.to_string(), limit);
+			state.hosts.remove(host);
+		}
+		return self;
+	}
+
+	/// Set the maximum duration a request waits for the limits to allow it. If it would wait longer,
+	/// it fails with a `WouldBlock` error instead. A zero duration makes requests fail fast
+	pub fn max_wait(self, max_wait: Duration) -> RateLimiter {
+		self.lock().max_wait = Some(max_wait);
+		return self;
+	}
+
+	/// Wait until a request to `host` is allowed, and return a permit counting it in flight until it is dropped
+	pub fn acquire(&self, host: &str) -> Result<Permit, Error> {
+		let start = Instant::now();
+		let mut state = self.lock();
+		loop {
+			let now = Instant::now();
+			let wait = match state.wait(host, now) {
+				Wait::Ready => {
+					state.global.take();
+					state.host(host).take();
+					return Ok(Permit{limiter: self.clone(), host: host.to_string()});
+				},
+				Wait::For(d) => Some(d),
+				Wait::Release => None
+			};
+			let remaining = match state.max_wait {
+				Some(max) => {
+					let elapsed = now.duration_since(start);
+					if elapsed >= max || wait.map(|w| elapsed.checked_add(w).map(|t| t > max).unwrap_or(true)).unwrap_or(false) {
+						return Err(Error::new(ErrorKind::WouldBlock, format!("Rate limit reached for {}", host)));
+					}
+					Some(max - elapsed)
+				},
+				None => None
+			};
+			state = match wait.or(remaining) {
+				Some(t) => match self.shared.released.wait_timeout(state, t) {
+					Ok(r) => r.0,
+					Err(e) => e.into_inner().0
+				},
+				None => match self.shared.released.wait(state) {
+					Ok(s) => s,
+					Err(e) => e.into_inner()
+				}
+			};
+		}
+	}
+
+	/// Adapt the limits of `host` to the rate limiting properties of a reply from it : `RateLimit`, or
+	/// `RateLimit-Remaining` and `RateLimit-Reset`, possibly prefixed with `X-`.
+	///
+	/// Requests to `host` are suspended until the reset if no request remains, and the available
+	/// tokens never exceed the remaining requests.
+	pub fn update<'a, I: IntoIterator<Item=(&'a String, &'a String)>>(&self, host: &str, header: I) {
+		let (mut remaining, mut reset) = (None, None);
+		for (k, v) in header {
+			let name = k.to_ascii_lowercase();
+			let name = if name.starts_with("x-") { &name[2..] } else { &name[..] };
+			match name {
+				"ratelimit-remaining" => remaining = u64::from_str(v.trim()).ok(),
+				"ratelimit-reset" => reset = parse_reset(v),
+				"ratelimit" => for item in v.split(',') {
+					let mut splt = item.splitn(2, '=');
+					match (splt.next().map(|s| s.trim()), splt.next()) {
+						(Some("remaining"), Some(r)) => remaining = u64::from_str(r.trim()).ok(),
+						(Some("reset"), Some(r)) => reset = parse_reset(r),
+						_ => {}
+					}
+				},
+				_ => {}
+			}
+		}
+		let remaining = match remaining {
+			Some(r) => r,
+			None => return
+		};
+		let mut state = self.lock();
+		let bucket = state.host(host);
+		if remaining == 0 {
+			bucket.blocked_until = reset.and_then(|r| Instant::now().checked_add(r));
+		} else if bucket.limit.rate.is_some() && bucket.tokens > remaining as f64 {
+			bucket.tokens = remaining as f64;
+		}
+	}
+
+	fn release(&self, host: &str) {
+		{
+			let mut state = self.lock();
+			state.global.in_flight = state.global.in_flight.saturating_sub(1);
+			let bucket = state.host(host);
+			bucket.in_flight = bucket.in_flight.saturating_sub(1);
+		}
+		self.shared.released.notify_all();
+	}
+}
+
+/// Parse a reset delay, given in seconds, or as a UNIX timestamp by some servers
+fn parse_reset(value: &str) -> Option<Duration> {
+	let secs = match u64::from_str(value.trim()) {
+		Ok(s) => s,
+		Err(_) => return None
+	};
+	if secs < 1000000000 {
+		return Some(Duration::from_secs(secs));
+	}
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+	return Some(Duration::from_secs(secs.saturating_sub(now)));
+}
+
+/// A request allowed by a `RateLimiter`, counted in flight until the permit is dropped
+pub struct Permit {
+	limiter: RateLimiter,
+	host: String
+}
+
+impl Drop for Permit {
+	fn drop(&mut self) {
+		self.limiter.release(&self.host);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use std::thread;
+	use std::time::{Duration, Instant};
+	use super::*;
+
+	fn ms(ms: u64) -> Duration {
+		return Duration::from_millis(ms);
+	}
+
+	#[test]
+	fn bucket_refill() {
+		let mut bucket = Bucket::new(Limit::new().rate(2.0, 3));
+		let start = bucket.last;
+		for _ in 0..3 {
+			match bucket.wait(start) {
+				Wait::Ready => bucket.take(),
+				_ => panic!("burst not allowed")
+			}
+		}
+		match bucket.wait(start) {
+			Wait::For(d) => assert!(d > ms(499) && d <= ms(501), "{:?}", d),
+			_ => panic!("empty bucket allowed a request")
+		}
+		match bucket.wait(start + ms(250)) {
+			Wait::For(d) => assert!(d > ms(249) && d <= ms(251), "{:?}", d),
+			_ => panic!("request allowed before refill")
+		}
+		assert_eq!(bucket.wait(start + ms(500)), Wait::Ready);
+		bucket.refill(start + Duration::from_secs(60));
+		assert_eq!(bucket.tokens, 3.0);
+	}
+
+	#[test]
+	fn bucket_in_flight() {
+		let mut bucket = Bucket::new(Limit::new().max_in_flight(1));
+		let now = Instant::now();
+		assert_eq!(bucket.wait(now), Wait::Ready);
+		bucket.take();
+		assert_eq!(bucket.wait(now), Wait::Release);
+	}
+
+	#[test]
+	fn global_and_host_limits() {
+		let limiter = RateLimiter::new()
+			.global(Limit::new().max_in_flight(3))
+			.per_host(Limit::new().max_in_flight(2))
+			.host("special", Limit::new().max_in_flight(1))
+			.max_wait(ms(0));
+		let a = limiter.acquire("a").unwrap();
+		let _b = limiter.acquire("a").unwrap();
+		assert_eq!(limiter.acquire("a").err().unwrap().kind(), ErrorKind::WouldBlock);
+		let _c = limiter.acquire("special").unwrap();
+		assert!(limiter.acquire("special").is_err());
+		// The global limit is reached
+		assert!(limiter.acquire("b").is_err());
+		drop(a);
+		let _d = limiter.acquire("b").unwrap();
+	}
+
+	#[test]
+	fn waiting_for_a_release() {
+		let limiter = RateLimiter::new().global(Limit::new().max_in_flight(1)).max_wait(Duration::from_secs(5));
+		let permit = limiter.acquire("a").unwrap();
+		let other = limiter.clone();
+		let waiter = thread::spawn(move || other.acquire("b").map(|_| ()));
+		thread::sleep(ms(50));
+		drop(permit);
+		assert!(waiter.join().unwrap().is_ok());
+	}
+
+	#[test]
+	fn rate_limit_properties() {
+		let limiter = RateLimiter::new().per_host(Limit::new().rate(1.0, 10)).max_wait(ms(0));
+		let mut header = HashMap::new();
+		header.insert("X-RateLimit-Remaining".to_string(), "2".to_string());
+		limiter.update("a", header.iter());
+		assert!(limiter.acquire("a").is_ok());
+		assert!(limiter.acquire("a").is_ok());
+		assert!(limiter.acquire("a").is_err());
+		let mut header = HashMap::new();
+		header.insert("RateLimit".to_string(), "limit=10, remaining=0, reset=30".to_string());
+		limiter.update("b", header.iter());
+		assert!(limiter.acquire("b").is_err());
+		assert!(limiter.acquire("c").is_ok());
+		assert_eq!(parse_reset("30"), Some(Duration::from_secs(30)));
+		assert_eq!(parse_reset("1000000000"), Some(Duration::from_secs(0)));
+		assert_eq!(parse_reset("soon"), None);
+	}
+}
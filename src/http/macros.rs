@@ -41,11 +41,12 @@ macro_rules! map {
 
 /// Print debug message only if built in debug mode
 ///
-/// This macro will print given message if `debug_assertions` build configuration is set.
 /// The message will be formated with its additional arguments with `format!` macro and will
-/// contain a header with file name and line number
+/// contain a header with file name and line number. When the "log" or "tracing" feature is enabled,
+/// it is emitted as a debug event with the `http` target instead, whatever the build configuration.
 /// # Example
 /// ```no_run
+/// # #![allow(deprecated)]
 /// # #[macro_use] extern crate http;
 /// # fn main() {
 /// debug!("This is a debug message");
@@ -53,16 +54,13 @@ macro_rules! map {
 /// # }
 /// ```
 #[macro_export]
+#[deprecated(note = "enable the \"log\" or \"tracing\" feature to get the diagnostic events of clients")]
 macro_rules! debug {
 	($fmt:expr) => (
-		if cfg!(debug_assertions){
-			println!(concat!("[DEBUG ", file!(), " line ", line!(), "] ", $fmt))
-		}
+		$crate::__debug(cfg!(debug_assertions), file!(), line!(), format_args!($fmt))
 	);
-    ($fmt:expr, $($arg:tt)*) => (
-    	if cfg!(debug_assertions){
-    		println!(concat!("[DEBUG ", file!(), " line ", line!(), "] ", $fmt), $($arg)*)
-		}
+	($fmt:expr, $($arg:tt)*) => (
+		$crate::__debug(cfg!(debug_assertions), file!(), line!(), format_args!($fmt, $($arg)*))
 	);
 }
 
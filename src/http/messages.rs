@@ -1,28 +1,54 @@
 //! HTTP messages definitions
+use std::cmp;
 use std::collections::HashMap;
-use std::io::{BufReader, Error, ErrorKind};
-use std::str;
+use std::io::{BufReader, Cursor, Error, ErrorKind};
 use std::str::FromStr;
 use std::collections::hash_map::{Iter, Keys};
 use std::fmt;
 use std::io::prelude::*;
 
 use super::constants::properties;
+use super::methods::Method;
+use super::body::{BodyReader, Framing};
+use super::encoding::{Coding, Decoder};
+use super::charset;
+use super::mime::MediaType;
+use super::multipart::MultipartReader;
+use super::headers::Header;
+use super::cache::CacheStatus;
+use super::timing::{Timings, SharedTimings};
+use super::limit::Permit;
+
+/// Maximum number of bytes allocated ahead for a reply content, whatever its `Content-Length`
+const MAX_PREALLOCATION: usize = 64 * 1024;
 
 /// A structure that represents an HTTP reply
 ///
 /// It contains an already parsed header information, and offers
-/// a `BufReader<T>` to read reply content
+/// a `BodyReader` to read reply content
+///
+/// If the reply content has been encoded with content codings supported by the crate (see `encoding` module),
+/// the `Content-Encoding` and `Content-Length` properties are removed from the reply header, and the content
+/// is transparently decoded by `read_all` and `read_string`.
 pub struct HttpReply<T: Read> {
 	version: String,
 	code: u32,
 	status: String,
 	header: HashMap<String, String>,
-	reader: BufReader<T>
+	codings: Vec<Coding>,
+	cache_status: Option<CacheStatus>,
+	timings: Option<SharedTimings>,
+	body: BodyReader<BufReader<T>>
 }
 
+/// An `HttpReply` whose content has been entirely read in memory.
+///
+/// It doesn't borrow anything from the client it comes from, so it can be kept while the
+/// client sends other requests, moved to another thread or returned from a function.
+pub type BufferedReply = HttpReply<Cursor<Vec<u8>>>;
+
 impl <T: Read> HttpReply<T> {
-	/// Contruct a new HttpReply by parsing the input from `reader`
+	/// Contruct a new HttpReply by parsing the input from `reader`, assuming it replies to a `GET` request
 	/// # Examples
 	/// ```no_run
 	/// use std::net::TcpStream;
@@ -33,7 +59,49 @@ impl <T: Read> HttpReply<T> {
 	/// let reader = BufReader::new(socket);
 	/// let r = HttpReply::parse(reader).unwrap();
 	/// ```
-	pub fn parse(mut reader: BufReader<T>) -> Result<HttpReply<T>, Error> {
+	pub fn parse(reader: BufReader<T>) -> Result<HttpReply<T>, Error> {
+		return HttpReply::parse_for(Method::GET, reader);
+	}
+	
+	/// Contruct a new HttpReply by parsing the input from `reader`, replying to a `method` request
+	///
+	/// Interim `1xx` replies, except `101 Switching Protocols`, are skipped. The reply content
+	/// is bounded according to the request method, the reply status and its header.
+	pub fn parse_for(method: Method, reader: BufReader<T>) -> Result<HttpReply<T>, Error> {
+		return HttpReply::parse_with(method, reader, true);
+	}
+	
+	/// Contruct a new HttpReply by parsing the input from `reader`, replying to a `method` request.
+	///
+	/// If `decode` is false, the reply content is never decoded and its header is left untouched.
+	pub fn parse_with(method: Method, mut reader: BufReader<T>, decode: bool) -> Result<HttpReply<T>, Error> {
+		loop {
+			let (version, code, status, mut header) = try!(HttpReply::parse_head(&mut reader));
+			if code >= 100 && code < 200 && code != 101 {
+				continue;
+			}
+			let framing = try!(Framing::for_reply(
+				method,
+				code,
+				find_property(&header, properties::TRANSFER_ENCODING).map(|s| &s[..]),
+				find_property(&header, properties::CONTENT_LENGTH).map(|s| &s[..])
+			));
+			let mut codings = Vec::new();
+			if decode && framing != Framing::Empty {
+				let list = find_property(&header, properties::CONTENT_ENCODING).and_then(|v| Coding::parse_list(v));
+				if let Some(list) = list {
+					if !list.is_empty() && list.iter().all(|c| c.is_supported()) {
+						header.retain(|k, _| !k.eq_ignore_ascii_case(properties::CONTENT_ENCODING) && !k.eq_ignore_ascii_case(properties::CONTENT_LENGTH));
+						codings = list;
+					}
+				}
+			}
+			let body = BodyReader::new(reader, framing);
+			return Ok(HttpReply{version: version, code: code, status: status, header: header, codings: codings, cache_status: None, timings: None, body: body});
+		}
+	}
+	
+	fn parse_head(reader: &mut BufReader<T>) -> Result<(String, u32, String, HashMap<String, String>), Error> {
 		let code: u32;
 		let version: String;
 		let status: String;
@@ -66,9 +134,7 @@ impl <T: Read> HttpReply<T> {
 				line.clear();
 			}
 		}
-		
-		let reply = HttpReply{version: version, code: code, status: status, header: header, reader: reader};
-		return Ok(reply);
+		return Ok((version, code, status, header));
 	}
 	
 	/// Get the `Content-Length` property from header
@@ -82,9 +148,21 @@ impl <T: Read> HttpReply<T> {
 		};
 	}
 	
-	/// Return a `BufReader` to read the reply's content
-	pub fn get_reader(&mut self) -> &mut BufReader<T> {
-		return &mut self.reader;
+	/// Return a `BodyReader` to read the reply's raw content, as sent by the server. It returns EOF at the end of the reply
+	pub fn get_reader(&mut self) -> &mut BodyReader<BufReader<T>> {
+		return &mut self.body;
+	}
+	
+	/// Return a reader decoding the reply's content according to its content codings
+	///
+	/// Decoding state is held by the returned reader, so the whole content should be read through a single decoded reader.
+	pub fn get_decoded_reader(&mut self) -> Result<Decoder<&mut BodyReader<BufReader<T>>>, Error> {
+		return Decoder::new(&mut self.body, &self.codings);
+	}
+	
+	/// Get the content codings which are decoded from the reply's content, in the order they have been applied
+	pub fn get_content_codings(&self) -> &[Coding] {
+		return &self.codings;
 	}
 	
 	/// Get the HTTP version from reply. Returns a string like `"HTTP/1.0"`
@@ -107,6 +185,61 @@ impl <T: Read> HttpReply<T> {
 		return self.header.get(key);
 	}
 	
+	/// Get a typed property from reply header. Returns `None` if the property is missing or cannot be parsed
+	pub fn get_typed<H: Header>(&self) -> Option<H> {
+		return find_property(&self.header, H::name()).and_then(|v| H::parse(v).ok());
+	}
+	
+	/// Set a typed property in reply header, replacing any previous value
+	pub fn set_typed<H: Header>(&mut self, value: H) {
+		self.header.retain(|k, _| !k.eq_ignore_ascii_case(H::name()));
+		self.header.insert(H::name().to_string(), value.to_string());
+	}
+	
+	/// Set a property in reply header
+	pub fn set_property(&mut self, key: String, value: String) {
+		self.header.insert(key, value);
+	}
+	
+	/// Remove a property from reply header, ignoring the case of its name
+	pub fn unset_property(&mut self, key: &str) {
+		self.header.retain(|k, _| !k.eq_ignore_ascii_case(key));
+	}
+	
+	/// Get the cache status of the reply, if it went through a `cache::CachingClient`
+	pub fn get_cache_status(&self) -> Option<CacheStatus> {
+		return self.cache_status;
+	}
+	
+	/// Set the cache status of the reply
+	pub fn set_cache_status(&mut self, status: CacheStatus) {
+		self.cache_status = Some(status);
+	}
+	
+	/// Get the timings of the request and of its reply, if it has been received by a client.
+	///
+	/// While the reply content is being read, the byte counts are updated, and the completion of
+	/// the content is known once it has been entirely read.
+	pub fn get_timings(&self) -> Option<Timings> {
+		return self.timings.as_ref().map(|t| {
+			let mut timings = t.get();
+			if self.body.is_done() {
+				timings.mark_body_complete();
+			}
+			timings
+		});
+	}
+	
+	/// Keep a rate limiter `permit` until the reply content has been entirely read, or the reply is dropped
+	pub fn hold(&mut self, permit: Permit) {
+		self.body.hold(permit);
+	}
+	
+	/// Set the timings of the request, updated by the connection the reply is read from
+	pub fn set_timings(&mut self, timings: SharedTimings) {
+		self.timings = Some(timings);
+	}
+	
 	/// Return an iterator over properties names set in reply header
 	pub fn get_properties_name(&self) -> Keys<String, String> {
 		return self.header.keys();
@@ -119,21 +252,117 @@ impl <T: Read> HttpReply<T> {
 	
 	/// Read all the reply content into a buffer and return this buffer
 	pub fn read_all(&mut self) -> Result<Vec<u8>, Error> {
-		let len = try!(self.get_length());
-		let mut data: Vec<u8> = vec![0u8; len];
-		let mut reader = self.get_reader();
-		try!(reader.read(&mut data));
+		// The announced length is not trusted beyond a reasonable preallocation
+		let mut data: Vec<u8> = Vec::with_capacity(cmp::min(self.get_length().unwrap_or(0), MAX_PREALLOCATION));
+		try!(try!(self.get_decoded_reader()).read_to_end(&mut data));
 		return Ok(data);
 	}
 	
+	/// Read all the reply content and return a `BufferedReply` holding it,
+	/// releasing the underlying reader
+	/// # Example
+	/// ```no_run
+	/// use http::client::*;
+	/// use http::methods::Method;
+	/// use http::messages::BufferedReply;
+	///
+	/// fn fetch(client: &mut HttpClient, path: &str) -> BufferedReply {
+	/// 	return client.send(Method::GET, path, None, None).unwrap().into_buffered().unwrap();
+	/// }
+	/// ```
+	pub fn into_buffered(mut self) -> Result<BufferedReply, Error> {
+		let data = try!(self.read_all());
+		let framing = Framing::Length(data.len() as u64);
+		// The timings are complete, and must not be updated by later requests on the same connection
+		let timings = self.get_timings().map(SharedTimings::new);
+		return Ok(HttpReply{
+			version: self.version,
+			code: self.code,
+			status: self.status,
+			header: self.header,
+			codings: Vec::new(),
+			cache_status: self.cache_status,
+			timings: timings,
+			body: BodyReader::new(BufReader::new(Cursor::new(data)), framing)
+		});
+	}
+	
 	/// Read all the repl and return it into a `String`
+	///
+	/// The content is decoded according to its byte order mark if any, or the `charset` parameter of
+	/// the `Content-Type` property, or as UTF-8 by default. See `charset` module for supported charsets
 	pub fn read_string(&mut self) -> Result<String, Error> {
 		let data = try!(self.read_all());
-		let string = match str::from_utf8(&data) {
-			Ok(s) => s.to_string(),
-			Err(e) => return Err(Error::new(ErrorKind::Other, format!("Cannot convert content to utf8 string : {}", e)))
+		return charset::decode(&data, self.get_charset().as_ref().map(|s| &s[..]), false);
+	}
+	
+	/// Read all the reply and return it into a `String`, like `read_string` does, but replacing
+	/// malformed sequences with `U+FFFD REPLACEMENT CHARACTER`
+	pub fn read_string_lossy(&mut self) -> Result<String, Error> {
+		let data = try!(self.read_all());
+		return charset::decode(&data, self.get_charset().as_ref().map(|s| &s[..]), true);
+	}
+	
+	/// Get the `charset` parameter of the `Content-Type` property, if any
+	pub fn get_charset(&self) -> Option<String> {
+		return self.content_type().and_then(|t| t.charset().map(|c| c.to_string()));
+	}
+	
+	/// Get the parsed `Content-Type` property. Returns `None` if the property is missing or cannot be parsed
+	pub fn content_type(&self) -> Option<MediaType> {
+		return find_property(&self.header, properties::CONTENT_TYPE).and_then(|t| MediaType::parse(t).ok());
+	}
+	
+	/// Read the reply's `multipart/*` content, like `multipart/byteranges`, part by part.
+	/// Fails if the reply doesn't have a multipart content type with a boundary
+	pub fn multipart(&mut self) -> Result<MultipartReader<Decoder<&mut BodyReader<BufReader<T>>>>, Error> {
+		let boundary = match self.content_type() {
+			Some(ref t) if t.get_type() == "multipart" => t.boundary().map(|b| b.to_string()),
+			_ => None
+		};
+		let boundary = match boundary {
+			Some(b) => b,
+			None => return Err(Error::new(ErrorKind::InvalidData, "Reply content is not multipart"))
 		};
-		return Ok(string);
+		return Ok(MultipartReader::new(try!(self.get_decoded_reader()), &boundary));
+	}
+}
+
+/// Get a property from `header`, ignoring the case of its name
+fn find_property<'a>(header: &'a HashMap<String, String>, key: &str) -> Option<&'a String> {
+	return header.iter().find(|&(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v);
+}
+
+impl HttpReply<Cursor<Vec<u8>>> {
+	/// Build a `BufferedReply` from its parts. The content is already decoded
+	/// # Example
+	/// ```
+	/// # #[macro_use] extern crate http;
+	/// use http::messages::HttpReply;
+	///
+	/// # fn main() {
+	/// let header = smap!("Content-Type" => "text/plain; charset=ISO-8859-1");
+	/// let mut reply = HttpReply::from_parts("HTTP/1.1", 200, "OK", header, b"caf\xe9".to_vec());
+	/// assert_eq!(reply.read_string().unwrap(), "café");
+	/// # }
+	/// ```
+	pub fn from_parts(version: &str, code: u32, status: &str, header: HashMap<String, String>, body: Vec<u8>) -> BufferedReply {
+		let framing = Framing::Length(body.len() as u64);
+		return HttpReply{
+			version: version.to_string(),
+			code: code,
+			status: status.to_string(),
+			header: header,
+			codings: Vec::new(),
+			cache_status: None,
+			timings: None,
+			body: BodyReader::new(BufReader::new(Cursor::new(body)), framing)
+		};
+	}
+	
+	/// Get the whole reply content, whatever has already been read
+	pub fn get_body(&self) -> &[u8] {
+		return self.body.get_ref().get_ref().get_ref();
 	}
 }
 
@@ -1,6 +1,10 @@
 //! Methods definitions
+use std::fmt;
+use std::str::FromStr;
+use std::io::{Error, ErrorKind};
 
 /// Supported HTTP methods
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Method {
 	GET,
 	POST,
@@ -29,8 +33,40 @@ impl Method {
 		}
 	}
 	
+	/// Return true if the method is idempotent, ie sending the same request several times has the same effect as sending it once
+	pub fn is_idempotent(&self) -> bool {
+		return match *self {
+			Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE => true,
+			Method::POST | Method::PATCH | Method::CONNECT => false
+		};
+	}
+	
 	/// Return the method name representation as an utf8 encoded byte slice
 	pub fn as_bytes(&self) -> &[u8] {
 		return self.as_slice().as_bytes();
 	}
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Method {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return f.write_str(self.as_slice());
+	}
+}
+
+impl FromStr for Method {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<Method, Error> {
+		return match s {
+			"GET" => Ok(Method::GET),
+			"POST" => Ok(Method::POST),
+			"PUT" => Ok(Method::PUT),
+			"DELETE" => Ok(Method::DELETE),
+			"HEAD" => Ok(Method::HEAD),
+			"TRACE" => Ok(Method::TRACE),
+			"OPTIONS" => Ok(Method::OPTIONS),
+			"PATCH" => Ok(Method::PATCH),
+			"CONNECT" => Ok(Method::CONNECT),
+			_ => Err(Error::new(ErrorKind::InvalidData, format!("Unsupported HTTP method : {}", s)))
+		};
+	}
+}
@@ -0,0 +1,274 @@
+//! Metrics of client activity
+//!
+//! Clients given a `Recorder` with `set_metrics` report their activity to it :
+//!
+//! * `http_client_requests_total` counter, labelled with `method`, `host` and `status`, which is the
+//! reply status code, or `error` if no reply could be read
+//! * `http_client_request_duration_seconds` histogram, labelled with `method`, `host` and `phase`, recording
+//! the time from the start of each request to the end of its phases, as given by `timing::Timings` :
+//! `resolve`, `connect`, `tls`, `request_written`, `first_byte` and `last_byte`, which is the end of the reply
+//! content if it has been entirely read
+//! * `http_client_sent_bytes_total` and `http_client_received_bytes_total` counters, labelled with `host`
+//! * `http_client_open_connections` gauge, labelled with `host`
+//!
+//! There are no idle or pooled connection gauges : clients keep no connection pool, each one holding at most
+//! a single connection to its host, so `http_client_open_connections` is the only connection gauge.
+//!
+//! Durations and byte counts are recorded when connections are closed. `Registry` is a built-in recorder,
+//! keeping the metrics in memory and rendering them in the Prometheus text format.
+//! # Example
+//! ```no_run
+//! use http::client::*;
+//! use http::methods::Method;
+//! use http::metrics::Registry;
+//!
+//! let registry = Registry::new();
+//! let mut client = HttpClient::new("www.example.com:80").unwrap();
+//! client.set_metrics(registry.clone());
+//! client.send(Method::GET, "/", None, None).unwrap().read_all().unwrap();
+//! // Serve this on a /metrics endpoint
+//! println!("{}", registry.render());
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::methods::Method;
+use super::timing::SharedTimings;
+
+/// Receiver of metrics. Metrics are identified by their name and their labels, given as name and value pairs
+pub trait Recorder: Send + Sync {
+	/// Add `value` to a counter
+	fn increment_counter(&self, name: &str, labels: &[(&str, &str)], value: u64);
+
+	/// Add `delta`, which may be negative, to a gauge
+	fn add_to_gauge(&self, name: &str, labels: &[(&str, &str)], delta: f64);
+
+	/// Record an observation of `value` in a histogram
+	fn record_histogram(&self, name: &str, labels: &[(&str, &str)], value: f64);
+}
+
+/// Default histogram buckets, in seconds
+const DEFAULT_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+type Labels = Vec<(String, String)>;
+
+enum Series {
+	Counter(u64),
+	Gauge(f64),
+	Histogram{bounds: Vec<f64>, counts: Vec<u64>, sum: f64, count: u64}
+}
+
+impl Series {
+	fn kind(&self) -> &'static str {
+		return match *self {
+			Series::Counter(_) => "counter",
+			Series::Gauge(_) => "gauge",
+			Series::Histogram{..} => "histogram"
+		};
+	}
+}
+
+struct Metrics {
+	buckets: Vec<f64>,
+	families: BTreeMap<String, BTreeMap<Labels, Series>>
+}
+
+impl Metrics {
+	/// Get the series of `name` with `labels`, created with `new` if missing
+	fn series<F: FnOnce(&[f64]) -> Series>(&mut self, name: &str, labels: &[(&str, &str)], new: F) -> &mut Series {
+		let buckets = &self.buckets;
+		let family = self.families.entry(name.to_string()).or_insert_with(BTreeMap::new);
+		return family.entry(key(labels)).or_insert_with(|| new(buckets));
+	}
+}
+
+/// Sorted owned copy of `labels`, identifying a series
+fn key(labels: &[(&str, &str)]) -> Labels {
+	let mut key: Labels = labels.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect();
+	key.sort();
+	return key;
+}
+
+/// Escape a label value for the Prometheus text format
+fn escape(value: &str) -> String {
+	return value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+}
+
+/// Format `labels`, with an additional `extra` label, as `{name="value",...}`
+fn format_labels(labels: &Labels, extra: Option<(&str, &str)>) -> String {
+	let mut all: Vec<String> = labels.iter().map(|&(ref k, ref v)| format!("{}=\"{}\"", k, escape(v))).collect();
+	if let Some((k, v)) = extra {
+		all.push(format!("{}=\"{}\"", k, escape(v)));
+	}
+	if all.is_empty() {
+		return String::new();
+	}
+	return format!("{{{}}}", all.join(","));
+}
+
+/// A recorder keeping metrics in memory, shared by all its clones
+///
+/// Metrics are rendered in the Prometheus text format with `render`.
+/// # Example
+/// ```
+/// use http::metrics::{Registry, Recorder};
+///
+/// let registry = Registry::new().buckets(&[0.1, 1.0]);
+/// registry.increment_counter("jobs_total", &[("queue", "default")], 2);
+/// registry.record_histogram("job_duration_seconds", &[], 0.5);
+/// assert_eq!(registry.get_counter("jobs_total", &[("queue", "default")]), Some(2));
+/// let text = registry.render();
+/// assert!(text.contains("jobs_total{queue=\"default\"} 2\n"));
+/// assert!(text.contains("job_duration_seconds_bucket{le=\"1\"} 1\n"));
+/// ```
+#[derive(Clone)]
+pub struct Registry {
+	inner: Arc<Mutex<Metrics>>
+}
+
+impl Registry {
+	/// Create a new empty registry, with histogram buckets suited to request durations in seconds
+	pub fn new() -> Registry {
+		return Registry{inner: Arc::new(Mutex::new(Metrics{buckets: DEFAULT_BUCKETS.to_vec(), families: BTreeMap::new()}))};
+	}
+
+	fn lock(&self) -> MutexGuard<Metrics> {
+		return match self.inner.lock() {
+			Ok(m) => m,
+			Err(e) => e.into_inner()
+		};
+	}
+
+	/// Set the upper bounds of the buckets of histograms created from now on
+	pub fn buckets(self, bounds: &[f64]) -> Registry {
+		{
+			let mut bounds = bounds.to_vec();
+			bounds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+			self.lock().buckets = bounds;
+		}
+		return self;
+	}
+
+	/// Get the value of a counter
+	pub fn get_counter(&self, name: &str, labels: &[(&str, &str)]) -> Option<u64> {
+		return match self.lock().families.get(name).and_then(|f| f.get(&key(labels))) {
+			Some(&Series::Counter(n)) => Some(n),
+			_ => None
+		};
+	}
+
+	/// Get the value of a gauge
+	pub fn get_gauge(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+		return match self.lock().families.get(name).and_then(|f| f.get(&key(labels))) {
+			Some(&Series::Gauge(n)) => Some(n),
+			_ => None
+		};
+	}
+
+	/// Render all the metrics in the Prometheus text exposition format
+	pub fn render(&self) -> String {
+		let metrics = self.lock();
+		let mut out = String::new();
+		for (name, family) in &metrics.families {
+			let kind = match family.values().next() {
+				Some(s) => s.kind(),
+				None => continue
+			};
+			let _ = writeln!(out, "# TYPE {} {}", name, kind);
+			for (labels, series) in family {
+				match *series {
+					Series::Counter(n) => { let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), n); },
+					Series::Gauge(v) => { let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), v); },
+					Series::Histogram{ref bounds, ref counts, sum, count} => {
+						let mut cumulative = 0;
+						for (bound, n) in bounds.iter().zip(counts) {
+							cumulative += *n;
+							let _ = writeln!(out, "{}_bucket{} {}", name, format_labels(labels, Some(("le", &bound.to_string()))), cumulative);
+						}
+						let _ = writeln!(out, "{}_bucket{} {}", name, format_labels(labels, Some(("le", "+Inf"))), count);
+						let _ = writeln!(out, "{}_sum{} {}", name, format_labels(labels, None), sum);
+						let _ = writeln!(out, "{}_count{} {}", name, format_labels(labels, None), count);
+					}
+				}
+			}
+		}
+		return out;
+	}
+}
+
+impl Recorder for Registry {
+	// Metrics recorded with another type than the one they were created with are ignored
+	fn increment_counter(&self, name: &str, labels: &[(&str, &str)], value: u64) {
+		if let Series::Counter(ref mut n) = *self.lock().series(name, labels, |_| Series::Counter(0)) {
+			*n += value;
+		}
+	}
+
+	fn add_to_gauge(&self, name: &str, labels: &[(&str, &str)], delta: f64) {
+		if let Series::Gauge(ref mut v) = *self.lock().series(name, labels, |_| Series::Gauge(0.0)) {
+			*v += delta;
+		}
+	}
+
+	fn record_histogram(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+		let new = |b: &[f64]| Series::Histogram{bounds: b.to_vec(), counts: vec![0; b.len()], sum: 0.0, count: 0};
+		if let Series::Histogram{ref bounds, ref mut counts, ref mut sum, ref mut count} = *self.lock().series(name, labels, new) {
+			if let Some(i) = bounds.iter().position(|b| value <= *b) {
+				counts[i] += 1;
+			}
+			*sum += value;
+			*count += 1;
+		}
+	}
+}
+
+fn seconds(d: Duration) -> f64 {
+	return d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9;
+}
+
+/// Count a request to `host` in `recorder`, with the given reply `status`
+pub fn count_request(recorder: &Recorder, method: Method, host: &str, status: &str) {
+	recorder.increment_counter("http_client_requests_total", &[("method", method.as_slice()), ("host", host), ("status", status)], 1);
+}
+
+/// Metrics of a connection, counted open until it is dropped, when the timings of its request are recorded
+pub struct ConnectionMetrics {
+	recorder: Arc<Recorder>,
+	method: Method,
+	host: String,
+	timings: SharedTimings
+}
+
+impl ConnectionMetrics {
+	/// Count a new connection to `host`, carrying a `method` request whose timings are `timings`
+	pub fn new(recorder: Arc<Recorder>, method: Method, host: &str, timings: SharedTimings) -> ConnectionMetrics {
+		recorder.add_to_gauge("http_client_open_connections", &[("host", host)], 1.0);
+		return ConnectionMetrics{recorder: recorder, method: method, host: host.to_string(), timings: timings};
+	}
+}
+
+impl Drop for ConnectionMetrics {
+	fn drop(&mut self) {
+		let host = &self.host[..];
+		self.recorder.add_to_gauge("http_client_open_connections", &[("host", host)], -1.0);
+		let timings = self.timings.get();
+		let phases = [
+			("resolve", timings.get_resolve()),
+			("connect", timings.get_connect()),
+			("tls", timings.get_tls()),
+			("request_written", timings.get_request_written()),
+			("first_byte", timings.get_first_byte()),
+			("last_byte", timings.get_last_byte())
+		];
+		for &(phase, time) in phases.iter() {
+			if let Some(d) = time {
+				let labels = [("method", self.method.as_slice()), ("host", host), ("phase", phase)];
+				self.recorder.record_histogram("http_client_request_duration_seconds", &labels, seconds(d));
+			}
+		}
+		self.recorder.increment_counter("http_client_sent_bytes_total", &[("host", host)], timings.get_sent());
+		self.recorder.increment_counter("http_client_received_bytes_total", &[("host", host)], timings.get_received());
+	}
+}
@@ -0,0 +1,217 @@
+//! Request and reply middlewares
+//!
+//! A `Pipeline` stands in front of any `Execute` implementation, including the boxed client returned by
+//! `http::open`, and runs a chain of `Middleware` around every request : each of them can modify the
+//! outgoing request, answer it with a synthesized reply instead of sending it, and inspect or transform
+//! the reply. Closures taking a `&mut HttpRequest` can be used as middlewares which only modify requests.
+//! # Example
+//! ```no_run
+//! use std::io::Error;
+//! use http::{open, Protocol};
+//! use http::client::{Execute, WithHeader};
+//! use http::methods::Method;
+//! use http::request::HttpRequest;
+//! use http::messages::BufferedReply;
+//! use http::middleware::{Pipeline, Middleware};
+//!
+//! struct Log;
+//!
+//! impl Middleware for Log {
+//! 	fn after(&mut self, request: &HttpRequest, reply: &mut BufferedReply) -> Result<(), Error> {
+//! 		println!("{} {} : {}", request.get_method(), request.get_target(), reply.get_code());
+//! 		return Ok(());
+//! 	}
+//! }
+//!
+//! let client = open(Protocol::HTTP, "www.example.com:80").unwrap();
+//! let mut pipeline = Pipeline::new(client)
+//! 	.with(|r: &mut HttpRequest| { r.set_property("Authorization".to_string(), "Bearer token".to_string()); Ok(()) })
+//! 	.with(Log);
+//! let reply = pipeline.execute(&HttpRequest::new(Method::GET, "/")).unwrap();
+//! # drop(reply);
+//! ```
+use std::io::Error;
+
+use super::client::Execute;
+use super::messages::BufferedReply;
+use super::request::HttpRequest;
+
+/// A hook run before and after the requests of a `Pipeline`
+pub trait Middleware {
+	/// Inspect or modify `request` before it is sent. Returning a reply answers the request with it :
+	/// the following middlewares are skipped, and the request is not sent.
+	/// Returning an error aborts the request. By default, the request is left untouched
+	fn before(&mut self, request: &mut HttpRequest) -> Result<Option<BufferedReply>, Error> {
+		let _ = request;
+		return Ok(None);
+	}
+
+	/// Inspect or modify the `reply` to `request`, as modified by the middlewares. Returning an error
+	/// makes the request fail. By default, the reply is left untouched
+	fn after(&mut self, request: &HttpRequest, reply: &mut BufferedReply) -> Result<(), Error> {
+		let _ = (request, reply);
+		return Ok(());
+	}
+}
+
+impl <F: FnMut(&mut HttpRequest) -> Result<(), Error>> Middleware for F {
+	fn before(&mut self, request: &mut HttpRequest) -> Result<Option<BufferedReply>, Error> {
+		try!(self(request));
+		return Ok(None);
+	}
+}
+
+/// An `Execute` implementation running requests through a chain of middlewares
+///
+/// `before` hooks are run in the order the middlewares have been added, and `after` hooks in the
+/// reverse order, so the first middleware sees the request first and the reply last. When a middleware
+/// answers a request itself, only the `after` hooks of the middlewares before it are run.
+pub struct Pipeline<E: Execute> {
+	inner: E,
+	middlewares: Vec<Box<Middleware>>
+}
+
+impl <E: Execute> Pipeline<E> {
+	/// Create a new pipeline without any middleware in front of `inner`
+	pub fn new(inner: E) -> Pipeline<E> {
+		return Pipeline{inner: inner, middlewares: Vec::new()};
+	}
+
+	/// Append `middleware` to the chain
+	pub fn with<M: Middleware + 'static>(mut self, middleware: M) -> Pipeline<E> {
+		self.push(middleware);
+		return self;
+	}
+
+	/// Append `middleware` to the chain
+	pub fn push<M: Middleware + 'static>(&mut self, middleware: M) {
+		self.middlewares.push(Box::new(middleware));
+	}
+
+	/// Get a reference to the wrapped client
+	pub fn get_ref(&self) -> &E {
+		return &self.inner;
+	}
+
+	/// Get a mutable reference to the wrapped client
+	pub fn get_mut(&mut self) -> &mut E {
+		return &mut self.inner;
+	}
+
+	/// Unwrap the pipeline, returning the wrapped client
+	pub fn into_inner(self) -> E {
+		return self.inner;
+	}
+}
+
+impl <E: Execute> Execute for Pipeline<E> {
+	fn get_host(&self) -> Option<String> {
+		return self.inner.get_host();
+	}
+
+	fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+		let mut request = request.clone();
+		let mut answered = None;
+		let mut entered = 0;
+		for m in self.middlewares.iter_mut() {
+			if let Some(reply) = try!(m.before(&mut request)) {
+				answered = Some(reply);
+				break;
+			}
+			entered += 1;
+		}
+		let mut reply = match answered {
+			Some(r) => r,
+			None => try!(self.inner.execute(&request))
+		};
+		for m in self.middlewares[..entered].iter_mut().rev() {
+			try!(m.after(&request, &mut reply));
+		}
+		return Ok(reply);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+	use std::collections::HashMap;
+	use std::io::ErrorKind;
+	use std::rc::Rc;
+	use super::*;
+	use super::super::client::WithHeader;
+	use super::super::messages::HttpReply;
+	use super::super::methods::Method;
+
+	type Log = Rc<RefCell<Vec<String>>>;
+
+	/// A middleware recording its hooks, and possibly answering requests
+	struct Record {
+		name: &'static str,
+		answer: bool,
+		log: Log
+	}
+
+	impl Middleware for Record {
+		fn before(&mut self, request: &mut HttpRequest) -> Result<Option<BufferedReply>, Error> {
+			self.log.borrow_mut().push(format!("before {}", self.name));
+			request.set_property(format!("X-{}", self.name), "1".to_string());
+			if self.answer {
+				return Ok(Some(HttpReply::from_parts("HTTP/1.1", 200, "OK", HashMap::new(), Vec::new())));
+			}
+			return Ok(None);
+		}
+
+		fn after(&mut self, _: &HttpRequest, reply: &mut BufferedReply) -> Result<(), Error> {
+			self.log.borrow_mut().push(format!("after {}", self.name));
+			reply.set_property(format!("X-{}", self.name), "1".to_string());
+			return Ok(());
+		}
+	}
+
+	/// The client at the end of the chain, recording the requests it executes
+	struct Inner(Log);
+
+	impl Execute for Inner {
+		fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+			let mut names: Vec<&String> = request.get_properties_name().collect();
+			names.sort();
+			self.0.borrow_mut().push(format!("execute {:?}", names));
+			return Ok(HttpReply::from_parts("HTTP/1.1", 204, "No Content", HashMap::new(), Vec::new()));
+		}
+	}
+
+	fn pipeline(answering: Option<&'static str>) -> (Pipeline<Inner>, Log) {
+		let log = Rc::new(RefCell::new(Vec::new()));
+		let mut pipeline = Pipeline::new(Inner(log.clone()));
+		for &name in &["a", "b", "c"] {
+			pipeline.push(Record{name: name, answer: answering == Some(name), log: log.clone()});
+		}
+		return (pipeline, log);
+	}
+
+	#[test]
+	fn hooks_order() {
+		let (mut pipeline, log) = pipeline(None);
+		let reply = pipeline.execute(&HttpRequest::new(Method::GET, "/")).unwrap();
+		assert_eq!(reply.get_code(), 204);
+		assert_eq!(*log.borrow(), vec!["before a", "before b", "before c", "execute [\"X-a\", \"X-b\", \"X-c\"]", "after c", "after b", "after a"]);
+	}
+
+	#[test]
+	fn answered_requests() {
+		let (mut pipeline, log) = pipeline(Some("b"));
+		let reply = pipeline.execute(&HttpRequest::new(Method::GET, "/")).unwrap();
+		assert_eq!(reply.get_code(), 200);
+		assert!(reply.get_property(&"X-a".to_string()).is_some());
+		assert!(reply.get_property(&"X-b".to_string()).is_none());
+		assert_eq!(*log.borrow(), vec!["before a", "before b", "after a"]);
+	}
+
+	#[test]
+	fn failing_middlewares() {
+		let (mut pipeline, log) = pipeline(None);
+		pipeline.push(|_: &mut HttpRequest| Err(Error::new(ErrorKind::PermissionDenied, "denied")));
+		assert_eq!(pipeline.execute(&HttpRequest::new(Method::GET, "/")).unwrap_err().kind(), ErrorKind::PermissionDenied);
+		assert_eq!(*log.borrow(), vec!["before a", "before b", "before c"]);
+	}
+}
@@ -0,0 +1,309 @@
+//! Media types parsing and content negotiation
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+use std::fmt;
+
+use super::constants::properties;
+use super::syntax::{Parser, is_token, split_list};
+
+/// A media type, like `text/html; charset=UTF-8`
+///
+/// Type, subtype and parameters names are case insensitive, and are stored in lower case.
+/// # Example
+/// ```
+/// use http::mime::MediaType;
+///
+/// let t = MediaType::parse("Multipart/Form-Data; boundary=\"a b\"").unwrap();
+/// assert_eq!(t.essence(), "multipart/form-data");
+/// assert_eq!(t.boundary(), Some("a b"));
+/// assert!(t.matches(&MediaType::parse("multipart/*").unwrap()));
+/// assert_eq!(t.to_string(), "multipart/form-data; boundary=\"a b\"");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MediaType {
+	main: String,
+	sub: String,
+	params: Vec<(String, String)>
+}
+
+impl MediaType {
+	/// Create a new media type without parameters
+	pub fn new(main: &str, sub: &str) -> MediaType {
+		return MediaType{main: main.to_ascii_lowercase(), sub: sub.to_ascii_lowercase(), params: Vec::new()};
+	}
+
+	/// Parse a media type, with its parameters. Parameters values may be quoted
+	pub fn parse(s: &str) -> Result<MediaType, Error> {
+		let mut parser = Parser::new(s);
+		let main = parser.token();
+		if !parser.eat(b'/') {
+			return Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse media type : {}", s)));
+		}
+		let sub = parser.token();
+		if main.is_empty() || sub.is_empty() {
+			return Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse media type : {}", s)));
+		}
+		let mut mt = MediaType::new(&main, &sub);
+		mt.params = try!(parser.params());
+		if !parser.at_end() {
+			return Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse media type : {}", s)));
+		}
+		return Ok(mt);
+	}
+
+	/// Add a parameter, replacing any previous value with the same name
+	pub fn with_param(mut self, name: &str, value: &str) -> MediaType {
+		let name = name.to_ascii_lowercase();
+		self.params.retain(|p| p.0 != name);
+		self.params.push((name, value.to_string()));
+		return self;
+	}
+
+	/// Get the main type, like `text` in `text/html`
+	pub fn get_type(&self) -> &str {
+		return &self.main;
+	}
+
+	/// Get the subtype, like `html` in `text/html`
+	pub fn get_subtype(&self) -> &str {
+		return &self.sub;
+	}
+
+	/// Get the media type without its parameters, like `text/html`
+	pub fn essence(&self) -> String {
+		return format!("{}/{}", self.main, self.sub);
+	}
+
+	/// Get the value of a parameter
+	pub fn get_param(&self, name: &str) -> Option<&str> {
+		return self.params.iter().find(|p| p.0.eq_ignore_ascii_case(name)).map(|p| &p.1[..]);
+	}
+
+	/// Return an iterator over the parameters names and values
+	pub fn params(&self) -> ::std::slice::Iter<(String, String)> {
+		return self.params.iter();
+	}
+
+	/// Get the `charset` parameter
+	pub fn charset(&self) -> Option<&str> {
+		return self.get_param("charset");
+	}
+
+	/// Get the `boundary` parameter of a `multipart/*` media type
+	pub fn boundary(&self) -> Option<&str> {
+		return self.get_param("boundary");
+	}
+
+	/// Return true if the type or the subtype is a `*` wildcard
+	pub fn is_wildcard(&self) -> bool {
+		return self.main == "*" || self.sub == "*";
+	}
+
+	/// Return true if this media type matches `other`, which may contain wildcards, like `text/*` or `*/*`.
+	/// Parameters of `other`, if any, must all be present with the same value in this media type
+	pub fn matches(&self, other: &MediaType) -> bool {
+		if other.main != "*" && other.main != self.main {
+			return false;
+		}
+		if other.sub != "*" && other.sub != self.sub {
+			return false;
+		}
+		return other.params.iter().all(|&(ref k, ref v)| match self.get_param(k) {
+			Some(mine) if k == "charset" => mine.eq_ignore_ascii_case(v),
+			Some(mine) => mine == v,
+			None => false
+		});
+	}
+}
+
+impl FromStr for MediaType {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<MediaType, Error> {
+		return MediaType::parse(s);
+	}
+}
+
+impl fmt::Display for MediaType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(write!(f, "{}/{}", self.main, self.sub));
+		for &(ref k, ref v) in &self.params {
+			if !v.is_empty() && v.bytes().all(is_token) {
+				try!(write!(f, "; {}={}", k, v));
+			} else {
+				try!(write!(f, "; {}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")));
+			}
+		}
+		return Ok(());
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+	Media,
+	Language,
+	Coding
+}
+
+/// A list of values weighted with a quality, as used in `Accept`, `Accept-Language` and `Accept-Encoding` properties
+///
+/// Values are matched according to the header they are built for : media types may contain
+/// wildcards, language ranges match languages tags they are a prefix of, and codings are matched exactly.
+/// `*` matches everything.
+/// # Example
+/// ```
+/// use http::mime::QualityList;
+///
+/// let accept = QualityList::accept().add("application/json", 1.0).add("text/*", 0.5);
+/// assert_eq!(accept.to_string(), "application/json, text/*;q=0.5");
+/// assert_eq!(accept.quality("text/html; charset=UTF-8"), 0.5);
+/// assert_eq!(accept.best_match(&["text/plain", "application/json"]), Some("application/json"));
+///
+/// let languages = QualityList::accept_language().add("fr", 1.0).add("en", 0.8);
+/// assert_eq!(languages.best_match(&["en-US", "fr-CA"]), Some("fr-CA"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityList {
+	kind: Kind,
+	items: Vec<(String, f32)>
+}
+
+impl QualityList {
+	/// Create an empty list of media types for an `Accept` property
+	pub fn accept() -> QualityList {
+		return QualityList{kind: Kind::Media, items: Vec::new()};
+	}
+
+	/// Create an empty list of language ranges for an `Accept-Language` property
+	pub fn accept_language() -> QualityList {
+		return QualityList{kind: Kind::Language, items: Vec::new()};
+	}
+
+	/// Create an empty list of content codings for an `Accept-Encoding` property
+	pub fn accept_encoding() -> QualityList {
+		return QualityList{kind: Kind::Coding, items: Vec::new()};
+	}
+
+	/// Parse the value of an `Accept`, `Accept-Language` or `Accept-Encoding` property, according to the property `name`
+	pub fn parse(name: &str, value: &str) -> Result<QualityList, Error> {
+		let mut list = if name.eq_ignore_ascii_case(properties::ACCEPT) {
+			QualityList::accept()
+		} else if name.eq_ignore_ascii_case(properties::ACCEPT_LANGUAGE) {
+			QualityList::accept_language()
+		} else if name.eq_ignore_ascii_case(properties::ACCEPT_ENCODING) {
+			QualityList::accept_encoding()
+		} else {
+			return Err(Error::new(ErrorKind::InvalidInput, format!("Not a negotiation property : {}", name)));
+		};
+		for item in split_list(value) {
+			let mut parser = Parser::new(item);
+			let mut value = parser.token();
+			if list.kind == Kind::Media && parser.eat(b'/') {
+				value = format!("{}/{}", value, parser.token());
+			}
+			let mut quality = 1.0;
+			let mut params = Vec::new();
+			for (k, v) in try!(parser.params()) {
+				if k == "q" {
+					quality = match f32::from_str(&v) {
+						Ok(q) => q,
+						Err(e) => return Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse quality : {}", e)))
+					};
+				} else {
+					params.push(format!("{}={}", k, v));
+				}
+			}
+			if !params.is_empty() {
+				value = format!("{};{}", value, params.join(";"));
+			}
+			if !value.is_empty() {
+				list = list.add(&value, quality);
+			}
+		}
+		return Ok(list);
+	}
+
+	/// Get the name of the property this list is built for
+	pub fn property_name(&self) -> &'static str {
+		return match self.kind {
+			Kind::Media => properties::ACCEPT,
+			Kind::Language => properties::ACCEPT_LANGUAGE,
+			Kind::Coding => properties::ACCEPT_ENCODING
+		};
+	}
+
+	/// Append `value` with the given `quality`, between 0 and 1
+	pub fn add(mut self, value: &str, quality: f32) -> QualityList {
+		let quality = if quality < 0.0 { 0.0 } else if quality > 1.0 { 1.0 } else { quality };
+		self.items.push((value.to_string(), quality));
+		return self;
+	}
+
+	/// Return an iterator over the values and their quality
+	pub fn iter(&self) -> ::std::slice::Iter<(String, f32)> {
+		return self.items.iter();
+	}
+
+	fn specificity(&self, range: &str, value: &str) -> Option<usize> {
+		if range == "*" {
+			return Some(0);
+		}
+		return match self.kind {
+			Kind::Media => match (MediaType::parse(value), MediaType::parse(range)) {
+				(Ok(v), Ok(r)) if v.matches(&r) => Some(1 + (r.main != "*") as usize + (r.sub != "*") as usize + r.params.len()),
+				_ => None
+			},
+			Kind::Language => {
+				let (range, value) = (range.to_ascii_lowercase(), value.to_ascii_lowercase());
+				if value == range || value.starts_with(&format!("{}-", range)) {
+					Some(range.len())
+				} else {
+					None
+				}
+			},
+			Kind::Coding => if range.eq_ignore_ascii_case(value) { Some(1) } else { None }
+		};
+	}
+
+	/// Get the quality of `value` according to the most specific matching item of the list.
+	/// Returns 0 if no item matches
+	pub fn quality(&self, value: &str) -> f32 {
+		let mut best: Option<(usize, f32)> = None;
+		for &(ref range, q) in &self.items {
+			if let Some(s) = self.specificity(range, value) {
+				if best.map(|b| s > b.0).unwrap_or(true) {
+					best = Some((s, q));
+				}
+			}
+		}
+		return best.map(|b| b.1).unwrap_or(0.0);
+	}
+
+	/// Pick the candidate with the highest quality. Candidates with a null quality are never picked,
+	/// and the first one wins in case of equality
+	pub fn best_match<'a>(&self, candidates: &[&'a str]) -> Option<&'a str> {
+		let mut best: Option<(&'a str, f32)> = None;
+		for c in candidates {
+			let q = self.quality(c);
+			if q > 0.0 && best.map(|b| q > b.1).unwrap_or(true) {
+				best = Some((c, q));
+			}
+		}
+		return best.map(|b| b.0);
+	}
+}
+
+impl fmt::Display for QualityList {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, &(ref value, q)) in self.items.iter().enumerate() {
+			if i > 0 {
+				try!(f.write_str(", "));
+			}
+			try!(f.write_str(value));
+			if q < 1.0 {
+				let q = format!("{:.3}", q);
+				try!(write!(f, ";q={}", q.trim_end_matches('0').trim_end_matches('.')));
+			}
+		}
+		return Ok(());
+	}
+}
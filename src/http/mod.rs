@@ -3,11 +3,36 @@ pub mod methods;
 pub mod constants;
 pub mod client;
 pub mod messages;
+pub mod request;
+pub mod body;
+pub mod encoding;
+pub mod multipart;
+pub mod form;
+pub mod charset;
+pub mod mime;
+pub mod headers;
+pub mod date;
+pub mod cache;
+pub mod range;
+pub mod retry;
+pub mod limit;
+pub mod breaker;
+pub mod middleware;
+pub mod timing;
+pub mod metrics;
+pub mod resolve;
+#[cfg(feature="json")]
+pub mod json;
+mod random;
+mod syntax;
+mod trace;
+#[doc(hidden)]
+pub use self::trace::debug_message as __debug;
 pub mod streams;
 
-use std::net::ToSocketAddrs;
 use std::io::Error;
 use self::client::*;
+use self::resolve::ToAuthority;
 
 /// Protocol implementations
 pub enum Protocol {
@@ -30,7 +55,7 @@ pub enum Protocol {
 /// // Do something with http
 /// # drop(http);
 /// ```
-pub fn open<A: ToSocketAddrs>(protocol: Protocol, addr: A) -> Result<Box<Http>, Error> {
+pub fn open<A: ToAuthority>(protocol: Protocol, addr: A) -> Result<Box<Http>, Error> {
 	let cli: Box<Http> = match protocol {
 		Protocol::HTTP => Box::new(try!(HttpClient::new(addr))),
 		#[cfg(feature="ssl")]
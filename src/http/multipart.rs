@@ -0,0 +1,424 @@
+//! Multipart bodies support
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::collections::HashMap;
+use std::collections::hash_map::Iter;
+use std::fs::File;
+use std::path::Path;
+use std::cmp;
+
+use super::constants::{properties, mimetypes};
+use super::headers::Header;
+use super::mime::MediaType;
+use super::random;
+
+enum Source {
+	Bytes(Vec<u8>),
+	Reader(Box<Read>, Option<u64>)
+}
+
+struct Part {
+	header: String,
+	source: Source
+}
+
+/// A `multipart/form-data` body builder
+///
+/// Parts are streamed when the body is written, so files are never entirely loaded in memory.
+/// All the builder methods consume and return the multipart body so they can be chained.
+/// # Example
+/// ```no_run
+/// use http::client::*;
+/// use http::methods::Method;
+/// use http::multipart::Multipart;
+///
+/// let form = Multipart::new()
+/// 	.text("title", "Holidays")
+/// 	.bytes("thumbnail", "thumb.png", "image/png", vec![0u8; 16])
+/// 	.file("picture", "/path/to/picture.jpg").unwrap();
+/// let mut client = HttpClient::new("www.example.com:80").unwrap();
+/// let reply = client.send_multipart(Method::POST, "/upload", None, form).unwrap();
+/// # drop(reply);
+/// ```
+pub struct Multipart {
+	boundary: String,
+	parts: Vec<Part>
+}
+
+impl Multipart {
+	/// Create a new empty multipart body, with a random boundary
+	pub fn new() -> Multipart {
+		return Multipart{boundary: format!("------------------------{}", random::alphanumeric(24)), parts: Vec::new()};
+	}
+
+	/// Add a text field
+	pub fn text(self, name: &str, value: &str) -> Multipart {
+		let header = part_header(name, None, None);
+		return self.add(header, Source::Bytes(value.as_bytes().to_vec()));
+	}
+
+	/// Add an in-memory file part, named `filename`, with the given `content_type`
+	pub fn bytes(self, name: &str, filename: &str, content_type: &str, data: Vec<u8>) -> Multipart {
+		let header = part_header(name, Some(filename), Some(content_type));
+		return self.add(header, Source::Bytes(data));
+	}
+
+	/// Add the file at `path` as an `application/octet-stream` part. The file name is taken from `path`
+	pub fn file<P: AsRef<Path>>(self, name: &str, path: P) -> Result<Multipart, Error> {
+		let path = path.as_ref();
+		let file = try!(File::open(path));
+		let len = try!(file.metadata()).len();
+		let filename = match path.file_name() {
+			Some(f) => f.to_string_lossy().into_owned(),
+			None => name.to_string()
+		};
+		return Ok(self.reader(name, &filename, mimetypes::APP_OCTET_STREAM, file, Some(len)));
+	}
+
+	/// Add a file part named `filename`, whose content is read from `reader`. If `length` is not provided,
+	/// the whole body length is unknown and it will be sent with chunked transfer coding
+	pub fn reader<R: Read + 'static>(self, name: &str, filename: &str, content_type: &str, reader: R, length: Option<u64>) -> Multipart {
+		let header = part_header(name, Some(filename), Some(content_type));
+		return self.add(header, Source::Reader(Box::new(reader), length));
+	}
+
+	fn add(mut self, header: String, source: Source) -> Multipart {
+		self.parts.push(Part{header: header, source: source});
+		while self.parts.iter().any(|p| match p.source {
+			Source::Bytes(ref d) => contains(d, self.boundary.as_bytes()),
+			_ => false
+		}) {
+			self.boundary = format!("------------------------{}", random::alphanumeric(24));
+		}
+		return self;
+	}
+
+	/// Get the boundary delimiting the parts
+	pub fn get_boundary(&self) -> &str {
+		return &self.boundary;
+	}
+
+	/// Get the value of the `Content-Type` property to send with this body
+	pub fn content_type(&self) -> String {
+		return format!("{}; boundary={}", mimetypes::MULTIPART_FORM_DATA, self.boundary);
+	}
+
+	/// Get the total length of the body, if the size of every part is known
+	pub fn content_length(&self) -> Option<u64> {
+		let delimiter = self.boundary.len() as u64 + 4;
+		let mut len = delimiter + 2;
+		for part in &self.parts {
+			let data = match part.source {
+				Source::Bytes(ref d) => d.len() as u64,
+				Source::Reader(_, Some(l)) => l,
+				Source::Reader(_, None) => return None
+			};
+			len += delimiter + part.header.len() as u64 + data + 2;
+		}
+		return Some(len);
+	}
+
+	/// Write the whole body to `writer`, streaming parts read from files or readers
+	pub fn write_to<W: Write + ?Sized>(self, writer: &mut W) -> Result<(), Error> {
+		for part in self.parts {
+			try!(write!(writer, "--{}\r\n{}", self.boundary, part.header));
+			match part.source {
+				Source::Bytes(d) => try!(writer.write_all(&d)),
+				Source::Reader(r, Some(len)) => {
+					// Stick to the announced length, which the body length has been computed from
+					if try!(io::copy(&mut r.take(len), writer)) < len {
+						return Err(Error::new(ErrorKind::UnexpectedEof, "Multipart part shorter than its announced length"));
+					}
+				},
+				Source::Reader(mut r, None) => { try!(io::copy(&mut r, writer)); }
+			}
+			try!(writer.write_all(b"\r\n"));
+		}
+		try!(write!(writer, "--{}--\r\n", self.boundary));
+		return Ok(());
+	}
+}
+
+/// Build the header of a part, including the empty line ending it
+fn part_header(name: &str, filename: Option<&str>, content_type: Option<&str>) -> String {
+	let mut header = format!("{}: form-data; name=\"{}\"", properties::CONTENT_DISPOSITION, escape(name));
+	if let Some(f) = filename {
+		header.push_str(&format!("; filename=\"{}\"", escape(f)));
+	}
+	header.push_str("\r\n");
+	if let Some(t) = content_type {
+		header.push_str(&format!("{}: {}\r\n", properties::CONTENT_TYPE, t));
+	}
+	header.push_str("\r\n");
+	return header;
+}
+
+/// Escape a field name or a file name the way browsers do, so it can be quoted
+fn escape(s: &str) -> String {
+	return s.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
+}
+
+fn contains(data: &[u8], pattern: &[u8]) -> bool {
+	return data.windows(pattern.len()).any(|w| w == pattern);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReadState {
+	/// Reading the preamble or the content of a part, up to the next delimiter
+	Data,
+	/// A delimiter starts at the current position
+	Delimiter,
+	/// The close delimiter has been read
+	Done
+}
+
+/// A reader of `multipart/*` contents, like `multipart/byteranges` or `multipart/mixed` replies
+///
+/// Parts are read one after the other with `next_part()`. Each part gives access to its header, and
+/// reads its content up to the next boundary. Parts which are not entirely read are skipped.
+/// # Example
+/// ```
+/// use std::io::Read;
+/// use http::multipart::MultipartReader;
+/// use http::headers::ContentRange;
+///
+/// let body = b"--sep\r\nContent-Range: bytes 0-4/20\r\n\r\nHello\r\n--sep\r\nContent-Range: bytes 15-19/20\r\n\r\nWorld\r\n--sep--\r\n";
+/// let mut reader = MultipartReader::new(&body[..], "sep");
+/// let mut texts = Vec::new();
+/// while let Some(mut part) = reader.next_part().unwrap() {
+/// 	let range: ContentRange = part.get_typed().unwrap();
+/// 	let mut text = String::new();
+/// 	part.read_to_string(&mut text).unwrap();
+/// 	texts.push((range.get_range().unwrap(), text));
+/// }
+/// assert_eq!(texts, vec![((0, 4), "Hello".to_string()), ((15, 19), "World".to_string())]);
+/// ```
+pub struct MultipartReader<R: Read> {
+	inner: R,
+	delimiter: Vec<u8>,
+	buf: Vec<u8>,
+	pos: usize,
+	state: ReadState
+}
+
+impl <R: Read> MultipartReader<R> {
+	/// Create a new reader of a multipart content read from `inner`, whose parts are delimited by `boundary`
+	pub fn new(inner: R, boundary: &str) -> MultipartReader<R> {
+		// The first delimiter may not be preceded by a line break, so one is inserted before the content
+		return MultipartReader{inner: inner, delimiter: format!("\r\n--{}", boundary).into_bytes(), buf: b"\r\n".to_vec(), pos: 0, state: ReadState::Data};
+	}
+
+	/// Get the next part, or `None` once the close delimiter has been read. The rest of the current part, if any, is skipped
+	pub fn next_part<'a>(&'a mut self) -> Result<Option<PartReader<'a, R>>, Error> {
+		let mut sink = [0u8; 4096];
+		while self.state == ReadState::Data {
+			try!(self.read_data(&mut sink));
+		}
+		if self.state == ReadState::Done {
+			return Ok(None);
+		}
+		self.pos += self.delimiter.len();
+		if try!(self.fill(2)) && &self.buf[self.pos..self.pos+2] == b"--" {
+			self.state = ReadState::Done;
+			return Ok(None);
+		}
+		// Transport padding after the delimiter is ignored
+		let line = try!(self.read_line());
+		if !line.trim().is_empty() {
+			return Err(Error::new(ErrorKind::InvalidData, "Unexpected data after multipart boundary"));
+		}
+		let mut header = HashMap::new();
+		loop {
+			let line = try!(self.read_line());
+			let line = line.trim();
+			if line.is_empty() {
+				break;
+			}
+			let mut splt = line.splitn(2, ':');
+			let key = splt.next().unwrap_or("").trim().to_string();
+			let value = match splt.next() {
+				Some(v) => v.trim().to_string(),
+				None => return Err(Error::new(ErrorKind::InvalidData, format!("Cannot parse part header : {}", line)))
+			};
+			header.insert(key, value);
+		}
+		self.state = ReadState::Data;
+		return Ok(Some(PartReader{header: header, reader: self}));
+	}
+
+	/// Unwrap this reader, returning the underlying reader. Buffered data is lost
+	pub fn into_inner(self) -> R {
+		return self.inner;
+	}
+
+	/// Make sure at least `min` bytes are buffered after the current position. Returns false if the content ends before
+	fn fill(&mut self, min: usize) -> Result<bool, Error> {
+		if self.pos > 0 && self.pos >= self.buf.len() / 2 {
+			self.buf.drain(..self.pos);
+			self.pos = 0;
+		}
+		let mut tmp = [0u8; 4096];
+		while self.buf.len() - self.pos < min {
+			let n = try!(self.inner.read(&mut tmp));
+			if n == 0 {
+				return Ok(false);
+			}
+			self.buf.extend_from_slice(&tmp[..n]);
+		}
+		return Ok(true);
+	}
+
+	fn read_line(&mut self) -> Result<String, Error> {
+		loop {
+			if let Some(i) = self.buf[self.pos..].iter().position(|&b| b == b'\n') {
+				let line = String::from_utf8_lossy(&self.buf[self.pos..self.pos+i]).into_owned();
+				self.pos += i + 1;
+				return Ok(line);
+			}
+			let available = self.buf.len() - self.pos;
+			if !try!(self.fill(available + 1)) {
+				return Err(Error::new(ErrorKind::UnexpectedEof, "Multipart content ended inside a part header"));
+			}
+		}
+	}
+
+	/// Read data up to the next delimiter
+	fn read_data(&mut self, out: &mut [u8]) -> Result<usize, Error> {
+		if self.state != ReadState::Data || out.is_empty() {
+			return Ok(0);
+		}
+		let len = self.delimiter.len();
+		if !try!(self.fill(len)) {
+			return Err(Error::new(ErrorKind::UnexpectedEof, "Multipart content ended without close delimiter"));
+		}
+		let available = &self.buf[self.pos..];
+		let end = match available.windows(len).position(|w| w == &self.delimiter[..]) {
+			Some(0) => {
+				self.state = ReadState::Delimiter;
+				return Ok(0);
+			},
+			Some(i) => i,
+			// The end of the buffer may be the beginning of a delimiter
+			None => available.len() - len + 1
+		};
+		let n = cmp::min(end, out.len());
+		out[..n].copy_from_slice(&available[..n]);
+		self.pos += n;
+		return Ok(n);
+	}
+}
+
+/// A part of a multipart content, read by a `MultipartReader`
+pub struct PartReader<'a, R: Read + 'a> {
+	header: HashMap<String, String>,
+	reader: &'a mut MultipartReader<R>
+}
+
+impl <'a, R: Read> PartReader<'a, R> {
+	/// Get the value of a property of the part header. The property name is case insensitive
+	pub fn get_property(&self, key: &str) -> Option<&String> {
+		return self.header.iter().find(|&(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v);
+	}
+
+	/// Get a property of the part header, parsed into its typed representation
+	pub fn get_typed<H: Header>(&self) -> Option<H> {
+		return self.get_property(H::name()).and_then(|v| H::parse(v).ok());
+	}
+
+	/// Return an iterator over the properties of the part header
+	pub fn iter(&self) -> Iter<String, String> {
+		return self.header.iter();
+	}
+
+	/// Get the parsed `Content-Type` of the part, if any
+	pub fn content_type(&self) -> Option<MediaType> {
+		return self.get_property(properties::CONTENT_TYPE).and_then(|t| MediaType::parse(t).ok());
+	}
+}
+
+impl <'a, R: Read> Read for PartReader<'a, R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		return self.reader.read_data(buf);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Cursor, ErrorKind};
+	use super::*;
+
+	#[test]
+	fn reader_parts_are_sent_with_their_announced_length() {
+		let form = Multipart::new().reader("a", "a.txt", "text/plain", Cursor::new(b"Hello World".to_vec()), Some(5));
+		let length = form.content_length().unwrap();
+		let mut body = Vec::new();
+		form.write_to(&mut body).unwrap();
+		assert_eq!(body.len() as u64, length);
+		assert!(!contains(&body, b"World"));
+
+		let form = Multipart::new().reader("a", "a.txt", "text/plain", Cursor::new(b"Hi".to_vec()), Some(5));
+		assert_eq!(form.write_to(&mut Vec::new()).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+	}
+
+	/// A reader returning a single byte at a time, so delimiters are split across reads
+	struct Trickle<'a>(&'a [u8]);
+
+	impl <'a> Read for Trickle<'a> {
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+			if self.0.is_empty() || buf.is_empty() {
+				return Ok(0);
+			}
+			buf[0] = self.0[0];
+			self.0 = &self.0[1..];
+			return Ok(1);
+		}
+	}
+
+	fn parts<R: Read>(mut reader: MultipartReader<R>) -> Result<Vec<(Option<String>, String)>, Error> {
+		let mut parts = Vec::new();
+		while let Some(mut part) = try!(reader.next_part()) {
+			let mut s = String::new();
+			try!(part.read_to_string(&mut s));
+			parts.push((part.get_property("content-disposition").cloned(), s));
+		}
+		return Ok(parts);
+	}
+
+	#[test]
+	fn written_form_is_read_back() {
+		let form = Multipart::new().text("title", "Hello\r\n--World").bytes("file", "a\"b.txt", "text/plain", b"data".to_vec());
+		let boundary = form.get_boundary().to_string();
+		let mut body = Vec::new();
+		form.write_to(&mut body).unwrap();
+		let parts = parts(MultipartReader::new(Trickle(&body), &boundary)).unwrap();
+		assert_eq!(parts, vec![
+			(Some("form-data; name=\"title\"".to_string()), "Hello\r\n--World".to_string()),
+			(Some("form-data; name=\"file\"; filename=\"a%22b.txt\"".to_string()), "data".to_string())
+		]);
+	}
+
+	#[test]
+	fn preamble_padding_and_epilogue_are_ignored() {
+		let body = b"preamble\r\n--sep  \r\nContent-Type: text/plain\r\n\r\none\r\n--sep\r\n\r\ntwo\r\n--sep--\r\nepilogue";
+		let mut reader = MultipartReader::new(&body[..], "sep");
+		{
+			let part = reader.next_part().unwrap().unwrap();
+			assert_eq!(part.content_type().unwrap().to_string(), "text/plain");
+			// The part is skipped without being read
+		}
+		let mut s = String::new();
+		reader.next_part().unwrap().unwrap().read_to_string(&mut s).unwrap();
+		assert_eq!(s, "two");
+		assert!(reader.next_part().unwrap().is_none());
+		assert!(reader.next_part().unwrap().is_none());
+	}
+
+	#[test]
+	fn malformed_multipart_contents() {
+		let truncated = b"--sep\r\n\r\none\r\n--sep\r\n\r\ntw";
+		assert_eq!(parts(MultipartReader::new(&truncated[..], "sep")).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+		let bad_header = b"--sep\r\nno colon\r\n\r\none\r\n--sep--\r\n";
+		assert_eq!(parts(MultipartReader::new(&bad_header[..], "sep")).unwrap_err().kind(), ErrorKind::InvalidData);
+		let garbage = b"--sepgarbage\r\n\r\none\r\n--sep--\r\n";
+		assert_eq!(parts(MultipartReader::new(&garbage[..], "sep")).unwrap_err().kind(), ErrorKind::InvalidData);
+	}
+}
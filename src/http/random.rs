@@ -0,0 +1,33 @@
+//! Non cryptographic random numbers, used for multipart boundaries and backoff jitter
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Return a random 64 bits number
+pub fn next_u64() -> u64 {
+	let mut hasher = RandomState::new().build_hasher();
+	hasher.write_usize(COUNTER.fetch_add(1, Ordering::Relaxed));
+	if let Ok(d) = SystemTime::now().duration_since(UNIX_EPOCH) {
+		hasher.write_u64(d.as_secs());
+		hasher.write_u32(d.subsec_nanos());
+	}
+	return hasher.finish();
+}
+
+/// Return a random string of `len` alphanumeric characters
+pub fn alphanumeric(len: usize) -> String {
+	const CHARS: &'static [u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+	let mut s = String::with_capacity(len);
+	let mut n = next_u64();
+	for i in 0..len {
+		if i % 10 == 9 {
+			n = next_u64();
+		}
+		s.push(CHARS[(n % CHARS.len() as u64) as usize] as char);
+		n /= CHARS.len() as u64;
+	}
+	return s;
+}
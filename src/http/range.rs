@@ -0,0 +1,235 @@
+//! Range requests and resumable downloads
+//!
+//! Range requests are built with `HttpRequest::range` and `HttpRequest::if_range`, and replies are
+//! checked with `HttpReply::check_range`. `download_to_file` uses them to resume interrupted downloads.
+//! # Example
+//! ```no_run
+//! use http::client::HttpClient;
+//! use http::methods::Method;
+//! use http::request::HttpRequest;
+//! use http::range;
+//!
+//! let mut client = HttpClient::new("www.example.com:80").unwrap();
+//! let request = HttpRequest::new(Method::GET, "/artifacts/large.tar");
+//! // If the transfer fails, calling this again resumes from what has already been written
+//! let length = range::download_to_file(&mut client, &request, "large.tar").unwrap();
+//! println!("Downloaded {} bytes", length);
+//! ```
+use std::io::{self, Read, Write, Error, ErrorKind};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::ffi::OsString;
+
+use super::messages::HttpReply;
+use super::request::HttpRequest;
+use super::client::HttpSend;
+use super::headers::{ByteRange, ContentRange, IfRange, ETag, LastModified, ContentType, Header};
+use super::constants::properties;
+
+/// How a reply answers a range request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Partial {
+	/// `206 Partial Content` with a single range, described by the `Content-Range` property
+	Range(ContentRange),
+	/// `206 Partial Content` with several ranges, enclosed in a `multipart/byteranges` content which can be read with `HttpReply::multipart`
+	Multipart,
+	/// `200 OK` : the server ignored the range, or the `If-Range` validator didn't match, and sent the whole content
+	Full
+}
+
+/// Return true if `received` is an acceptable answer to the `requested` range
+fn range_matches(requested: &ByteRange, received: (u64, u64), complete_length: Option<u64>) -> bool {
+	if let Some(length) = complete_length {
+		return requested.resolve(length) == Some(received);
+	}
+	return match *requested {
+		ByteRange::FromTo(first, last) => received.0 == first && received.1 <= last,
+		ByteRange::From(first) => received.0 == first,
+		ByteRange::Last(_) => true
+	};
+}
+
+impl <R: Read> HttpReply<R> {
+	/// Check the reply to a request of the given byte `ranges`
+	///
+	/// A `206 Partial Content` reply must enclose one of the requested ranges, unless it has a
+	/// `multipart/byteranges` content. A `200 OK` reply encloses the whole content. Other replies,
+	/// including `416 Range Not Satisfiable`, are errors.
+	/// # Example
+	/// ```
+	/// use std::io::{BufReader, Cursor};
+	/// use http::messages::HttpReply;
+	/// use http::headers::ByteRange;
+	/// use http::range::Partial;
+	///
+	/// let data = b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 100-199/1000\r\nContent-Length: 100\r\n\r\n".to_vec();
+	/// let reply = HttpReply::parse(BufReader::new(Cursor::new(data))).unwrap();
+	/// match reply.check_range(&[ByteRange::FromTo(100, 199)]).unwrap() {
+	/// 	Partial::Range(r) => assert_eq!(r.get_complete_length(), Some(1000)),
+	/// 	_ => unreachable!()
+	/// }
+	/// assert!(reply.check_range(&[ByteRange::From(200)]).is_err());
+	/// ```
+	pub fn check_range(&self, ranges: &[ByteRange]) -> Result<Partial, Error> {
+		match self.get_code() {
+			200 => return Ok(Partial::Full),
+			206 => {},
+			416 => return Err(Error::new(ErrorKind::InvalidInput, match self.get_typed::<ContentRange>().and_then(|r| r.get_complete_length()) {
+				Some(l) => format!("Range not satisfiable, the content has {} bytes", l),
+				None => "Range not satisfiable".to_string()
+			})),
+			code => return Err(Error::new(ErrorKind::Other, format!("Unexpected HTTP status for a range request : {} {}", code, self.get_status())))
+		}
+		if self.get_typed::<ContentType>().map(|t| t.0.essence() == "multipart/byteranges").unwrap_or(false) {
+			return Ok(Partial::Multipart);
+		}
+		let content_range = match self.get_typed::<ContentRange>() {
+			Some(r) => r,
+			None => return Err(Error::new(ErrorKind::InvalidData, "Partial content without a valid Content-Range property"))
+		};
+		let received = match content_range.get_range() {
+			Some(r) => r,
+			None => return Err(Error::new(ErrorKind::InvalidData, "Partial content without a range"))
+		};
+		let received_length = match received.1.checked_sub(received.0).and_then(|n| n.checked_add(1)) {
+			Some(n) => n,
+			None => return Err(Error::new(ErrorKind::InvalidData, format!("Invalid range received : {}", content_range)))
+		};
+		if let Ok(length) = self.get_length() {
+			if length as u64 != received_length {
+				return Err(Error::new(ErrorKind::InvalidData, "Content-Length doesn't match Content-Range"));
+			}
+		}
+		if !ranges.iter().any(|r| range_matches(r, received, content_range.get_complete_length())) {
+			return Err(Error::new(ErrorKind::InvalidData, format!("Unexpected range received : {}", content_range)));
+		}
+		return Ok(Partial::Range(content_range));
+	}
+
+	/// Get the validator to use in an `If-Range` property for later range requests on the same content :
+	/// the entity tag if it is strong, or the last modification date otherwise
+	pub fn get_validator(&self) -> Option<IfRange> {
+		return match self.get_typed::<ETag>() {
+			Some(tag) if !tag.is_weak() => Some(IfRange::ETag(tag)),
+			_ => self.get_typed::<LastModified>().map(|d| IfRange::Date(d.0))
+		};
+	}
+}
+
+/// Path of the file holding the validator of a partially downloaded file
+fn validator_path(path: &Path) -> PathBuf {
+	let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or(OsString::new());
+	name.push(".if-range");
+	return path.with_file_name(name);
+}
+
+fn read_validator(path: &Path) -> Option<IfRange> {
+	let mut s = String::new();
+	if File::open(path).and_then(|mut f| f.read_to_string(&mut s)).is_err() {
+		return None;
+	}
+	return IfRange::parse(&s).ok();
+}
+
+fn write_validator(path: &Path, validator: Option<IfRange>) -> Result<(), Error> {
+	return match validator {
+		Some(v) => File::create(path).and_then(|mut f| f.write_all(v.to_string().as_bytes())),
+		None => remove_validator(path)
+	};
+}
+
+fn remove_validator(path: &Path) -> Result<(), Error> {
+	return match fs::remove_file(path) {
+		Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+		r => r
+	};
+}
+
+/// Download the content replied to `request` into the file at `path`, and return the file length.
+///
+/// If the file already exists, only the missing bytes are requested, and appended to the file. The
+/// validator of the content, if any, is stored next to the file, in a file with the `.if-range` extension
+/// appended, while the download is incomplete. It is sent in an `If-Range` property when resuming, so if the
+/// content has changed in between, the server sends it whole and the file is rewritten from the start.
+/// Without a validator, the download is resumed unconditionally.
+///
+/// The content is requested with the `identity` coding, so the file holds the exact bytes of the representation.
+pub fn download_to_file<H: HttpSend + ?Sized, P: AsRef<Path>>(client: &mut H, request: &HttpRequest, path: P) -> Result<u64, Error> {
+	let path = path.as_ref();
+	let validator_path = validator_path(path);
+	let offset = match fs::metadata(path) {
+		Ok(m) => m.len(),
+		Err(ref e) if e.kind() == ErrorKind::NotFound => 0,
+		Err(e) => return Err(e)
+	};
+	let mut request = request.clone().header(properties::ACCEPT_ENCODING, "identity");
+	let range = ByteRange::From(offset);
+	if offset > 0 {
+		request = request.range(vec![range]);
+		if let Some(validator) = read_validator(&validator_path) {
+			request = request.if_range(validator);
+		}
+	}
+	let mut reply = try!(client.send_request(&request));
+	if reply.get_code() == 416 && offset > 0 {
+		// The file may already be complete
+		if reply.get_typed::<ContentRange>().and_then(|r| r.get_complete_length()) == Some(offset) {
+			try!(remove_validator(&validator_path));
+			return Ok(offset);
+		}
+	}
+	let mut file = match try!(reply.check_range(&[range])) {
+		Partial::Full => {
+			try!(write_validator(&validator_path, reply.get_validator()));
+			try!(File::create(path))
+		},
+		Partial::Range(_) => {
+			if !validator_path.exists() {
+				try!(write_validator(&validator_path, reply.get_validator()));
+			}
+			try!(OpenOptions::new().append(true).open(path))
+		},
+		Partial::Multipart => return Err(Error::new(ErrorKind::InvalidData, "Unexpected multipart content for a single range"))
+	};
+	try!(io::copy(reply.get_reader(), &mut file));
+	try!(file.sync_all());
+	try!(remove_validator(&validator_path));
+	return file.metadata().map(|m| m.len());
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{BufReader, Cursor};
+	use super::*;
+
+	fn reply(head: &str) -> HttpReply<Cursor<Vec<u8>>> {
+		return HttpReply::parse(BufReader::new(Cursor::new(head.as_bytes().to_vec()))).unwrap();
+	}
+
+	#[test]
+	fn received_range_must_match_a_requested_one() {
+		assert!(range_matches(&ByteRange::Last(10), (90, 99), Some(100)));
+		assert!(!range_matches(&ByteRange::Last(10), (80, 99), Some(100)));
+		assert!(range_matches(&ByteRange::FromTo(0, 99), (0, 49), None));
+		assert!(!range_matches(&ByteRange::FromTo(0, 99), (0, 149), None));
+		assert!(range_matches(&ByteRange::From(10), (10, 20), None));
+		assert!(!range_matches(&ByteRange::From(10), (11, 20), None));
+	}
+
+	#[test]
+	fn replies_to_range_requests() {
+		let ranges = [ByteRange::From(100)];
+		assert_eq!(reply("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").check_range(&ranges).unwrap(), Partial::Full);
+		let multipart = reply("HTTP/1.1 206 Partial\r\nContent-Type: multipart/byteranges; boundary=sep\r\n\r\n");
+		assert_eq!(multipart.check_range(&ranges).unwrap(), Partial::Multipart);
+		let single = reply("HTTP/1.1 206 Partial\r\nContent-Range: bytes 100-199/200\r\nContent-Length: 100\r\n\r\n");
+		assert_eq!(single.check_range(&ranges).unwrap(), Partial::Range(ContentRange::new(100, 199, Some(200))));
+		assert_eq!(single.check_range(&[ByteRange::From(0)]).unwrap_err().kind(), ErrorKind::InvalidData);
+		let wrong_length = reply("HTTP/1.1 206 Partial\r\nContent-Range: bytes 100-199/200\r\nContent-Length: 10\r\n\r\n");
+		assert_eq!(wrong_length.check_range(&ranges).unwrap_err().kind(), ErrorKind::InvalidData);
+		let missing = reply("HTTP/1.1 206 Partial\r\nContent-Length: 0\r\n\r\n");
+		assert_eq!(missing.check_range(&ranges).unwrap_err().kind(), ErrorKind::InvalidData);
+		let unsatisfiable = reply("HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */50\r\nContent-Length: 0\r\n\r\n");
+		assert_eq!(unsatisfiable.check_range(&ranges).unwrap_err().kind(), ErrorKind::InvalidInput);
+	}
+}
@@ -0,0 +1,241 @@
+//! HTTP requests definitions
+use std::collections::HashMap;
+use std::collections::hash_map::{Iter, Keys};
+use std::time::Duration;
+use std::io::{Error, Write};
+use std::fmt;
+
+use super::methods::Method;
+use super::client::WithHeader;
+use super::constants::{properties, mimetypes};
+use super::encoding::{self, Coding};
+use super::form::{self, Form};
+use super::headers::{Range, ByteRange, IfRange};
+
+/// An owned HTTP request
+///
+/// A request can be built once, then stored, cloned, logged or sent several times.
+/// All the builder methods consume and return the request so they can be chained.
+/// # Example
+/// ```
+/// use http::methods::Method;
+/// use http::request::HttpRequest;
+///
+/// let req = HttpRequest::new(Method::POST, "/search")
+/// 	.query("q", "rust http")
+/// 	.header("Accept", "text/html")
+/// 	.body(b"data".to_vec());
+/// assert_eq!(req.get_target(), "/search?q=rust%20http");
+/// assert_eq!(req.get_body(), Some(&b"data"[..]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+	method: Method,
+	target: String,
+	version: String,
+	header: HashMap<String, String>,
+	body: Option<Vec<u8>>,
+	timeout: Option<Duration>
+}
+
+impl HttpRequest {
+	/// Create a new `HTTP/1.1` request for `target` with an empty header and no body
+	pub fn new(method: Method, target: &str) -> HttpRequest {
+		return HttpRequest {
+			method: method,
+			target: target.to_string(),
+			version: "HTTP/1.1".to_string(),
+			header: HashMap::new(),
+			body: None,
+			timeout: None
+		};
+	}
+
+	/// Set the HTTP version written in the request line, like `"HTTP/1.0"`
+	pub fn version(mut self, version: &str) -> HttpRequest {
+		self.version = version.to_string();
+		return self;
+	}
+
+	/// Set a property in request header
+	pub fn header(mut self, key: &str, value: &str) -> HttpRequest {
+		self.header.insert(key.to_string(), value.to_string());
+		return self;
+	}
+
+	/// Append a percent-encoded `key=value` pair to the query string of the target
+	pub fn query(mut self, key: &str, value: &str) -> HttpRequest {
+		self.target.push(if self.target.contains('?') {'&'} else {'?'});
+		self.target.push_str(&form::percent_encode(key));
+		self.target.push('=');
+		self.target.push_str(&form::percent_encode(value));
+		return self;
+	}
+
+	/// Append all the fields of `form` to the query string of the target
+	pub fn query_form(mut self, form: &Form) -> HttpRequest {
+		if !form.is_empty() {
+			self.target.push(if self.target.contains('?') {'&'} else {'?'});
+			self.target.push_str(&form.encode());
+		}
+		return self;
+	}
+
+	/// Set the request body. The `Content-Length` property is updated accordingly
+	pub fn body(mut self, data: Vec<u8>) -> HttpRequest {
+		self.replace_property(properties::CONTENT_LENGTH, &data.len().to_string());
+		self.body = Some(data);
+		return self;
+	}
+
+	/// Set the property `key`, removing the properties with the same name in a different case
+	fn replace_property(&mut self, key: &str, value: &str) {
+		self.header.retain(|k, _| !k.eq_ignore_ascii_case(key));
+		self.header.insert(key.to_string(), value.to_string());
+	}
+
+	/// Set an already serialized JSON document as request body, with the matching `Content-Type`
+	pub fn json(mut self, json: &str) -> HttpRequest {
+		self.replace_property(properties::CONTENT_TYPE, mimetypes::APP_JSON);
+		return self.body(json.as_bytes().to_vec());
+	}
+
+	/// Set an url-encoded `form` as request body, with the matching `Content-Type`
+	pub fn form(mut self, form: &Form) -> HttpRequest {
+		self.replace_property(properties::CONTENT_TYPE, mimetypes::APP_FORM_URLENCODED);
+		return self.body(form.encode().into_bytes());
+	}
+
+	/// Encode the request body with `coding`, which must be either gzip or zstd.
+	/// The `Content-Encoding` and `Content-Length` properties are updated accordingly
+	/// # Example
+	/// ```no_run
+	/// use http::methods::Method;
+	/// use http::request::HttpRequest;
+	/// use http::encoding::Coding;
+	///
+	/// let req = HttpRequest::new(Method::POST, "/logs").body(b"a large log batch".to_vec()).compress(Coding::Gzip).unwrap();
+	/// # drop(req);
+	/// ```
+	pub fn compress(mut self, coding: Coding) -> Result<HttpRequest, Error> {
+		let data = match self.body.take() {
+			Some(d) => try!(encoding::encode(&d, coding)),
+			None => return Ok(self)
+		};
+		self.replace_property(properties::CONTENT_ENCODING, coding.as_slice());
+		return Ok(self.body(data));
+	}
+
+	/// Request only the given byte `ranges` of the content, with a `Range` property
+	pub fn range(mut self, ranges: Vec<ByteRange>) -> HttpRequest {
+		self.replace_property(properties::RANGE, &Range::bytes(ranges).to_string());
+		return self;
+	}
+
+	/// Make the `Range` property conditional : the full content is sent instead if `validator` doesn't match
+	/// the current representation
+	pub fn if_range(mut self, validator: IfRange) -> HttpRequest {
+		self.replace_property(properties::IF_RANGE, &validator.to_string());
+		return self;
+	}
+
+	/// Set the maximum duration allowed for the whole request
+	pub fn timeout(mut self, timeout: Duration) -> HttpRequest {
+		self.timeout = Some(timeout);
+		return self;
+	}
+
+	/// Get the request method
+	pub fn get_method(&self) -> Method {
+		return self.method;
+	}
+
+	/// Get the request target, ie the path with its query string
+	pub fn get_target(&self) -> &str {
+		return &self.target;
+	}
+
+	/// Get the HTTP version of the request
+	pub fn get_version(&self) -> &str {
+		return &self.version;
+	}
+
+	/// Get the request body, if any
+	pub fn get_body(&self) -> Option<&[u8]> {
+		return self.body.as_ref().map(|b| &b[..]);
+	}
+
+	/// Get the timeout set for the whole request, if any
+	pub fn get_timeout(&self) -> Option<Duration> {
+		return self.timeout;
+	}
+
+	/// Write the request line and header, including the terminating empty line, to `writer`
+	pub fn write_head<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), Error> {
+		try!(write!(writer, "{} {} {}\r\n", self.method.as_slice(), self.target, self.version));
+		for (k, v) in &self.header {
+			try!(write!(writer, "{}: {}\r\n", k, v));
+		}
+		try!(writer.write_all(b"\r\n"));
+		return Ok(());
+	}
+
+	/// Write the whole request in wire format to `writer`
+	pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), Error> {
+		try!(self.write_head(writer));
+		if let Some(ref data) = self.body {
+			try!(writer.write_all(data));
+		}
+		return Ok(());
+	}
+}
+
+impl WithHeader for HttpRequest {
+	fn get_property(&self, key: &String) -> Option<&String> {
+		return self.header.get(key);
+	}
+
+	fn set_property(&mut self, key: String, value: String) {
+		self.header.insert(key, value);
+	}
+
+	fn unset_property(&mut self, key: &String) {
+		self.header.remove(key);
+	}
+
+	fn get_properties_name(&self) -> Keys<String, String> {
+		return self.header.keys();
+	}
+
+	fn iter(&self) -> Iter<String, String> {
+		return self.header.iter();
+	}
+}
+
+impl fmt::Display for HttpRequest {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(write!(f, "{} {} {}\r\n", self.method, self.target, self.version));
+		for (k, v) in &self.header {
+			try!(write!(f, "{}: {}\r\n", k, v));
+		}
+		try!(write!(f, "\r\n"));
+		if let Some(ref data) = self.body {
+			try!(write!(f, "{}", String::from_utf8_lossy(data)));
+		}
+		return Ok(());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn body_properties_replace_properties_in_another_case() {
+		let req = HttpRequest::new(Method::POST, "/").header("content-type", "text/plain").header("content-length", "1").json("{}");
+		assert_eq!(req.iter().filter(|&(k, _)| k.eq_ignore_ascii_case(properties::CONTENT_TYPE)).count(), 1);
+		assert_eq!(req.iter().filter(|&(k, _)| k.eq_ignore_ascii_case(properties::CONTENT_LENGTH)).count(), 1);
+		assert_eq!(req.get_property(&properties::CONTENT_TYPE.to_string()), Some(&mimetypes::APP_JSON.to_string()));
+		assert_eq!(req.get_property(&properties::CONTENT_LENGTH.to_string()), Some(&"2".to_string()));
+	}
+}
@@ -0,0 +1,287 @@
+//! Host name resolution
+//!
+//! Clients resolve the host they send requests to each time they open a connection, with a `Resolver`.
+//! `SystemResolver` uses the resolver of the operating system, `CachingResolver` keeps the addresses
+//! resolved by another resolver for a while, and `StaticResolver` overrides the addresses of some hosts,
+//! like curl's `--resolve` option.
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use http::client::HttpClient;
+//! use http::resolve::{SystemResolver, CachingResolver, StaticResolver};
+//!
+//! let resolver = StaticResolver::new(CachingResolver::new(SystemResolver, Duration::from_secs(60)))
+//! 	.add_override("api.example.com:443:127.0.0.1").unwrap();
+//! let mut client = HttpClient::new("api.example.com:443").unwrap();
+//! client.set_resolver(resolver);
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6, IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A host name or IP address, with a port
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Authority {
+	host: String,
+	port: u16
+}
+
+impl Authority {
+	/// Create a new authority. IPv6 addresses are given without brackets
+	pub fn new(host: &str, port: u16) -> Authority {
+		return Authority{host: host.to_string(), port: port};
+	}
+
+	/// Parse an authority given as `host:port`, with IPv6 addresses enclosed in brackets
+	/// # Example
+	/// ```
+	/// use http::resolve::Authority;
+	///
+	/// let authority = Authority::parse("[::1]:8080").unwrap();
+	/// assert_eq!(authority.get_host(), "::1");
+	/// assert_eq!(authority.get_port(), 8080);
+	/// assert_eq!(authority.to_string(), "[::1]:8080");
+	/// assert!(Authority::parse("www.example.com").is_err());
+	/// ```
+	pub fn parse(s: &str) -> Result<Authority, Error> {
+		let s = s.trim();
+		let (host, port) = match s.rfind(':') {
+			Some(i) => (&s[..i], &s[i + 1..]),
+			None => return Err(Error::new(ErrorKind::InvalidInput, format!("Missing port in {}", s)))
+		};
+		let host = if host.starts_with('[') && host.ends_with(']') { &host[1..host.len() - 1] } else { host };
+		if host.is_empty() || host.contains(|c: char| c == '[' || c == ']' || c == '/' || c.is_whitespace()) {
+			return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid host in {}", s)));
+		}
+		return match u16::from_str(port) {
+			Ok(p) => Ok(Authority::new(host, p)),
+			Err(_) => Err(Error::new(ErrorKind::InvalidInput, format!("Invalid port in {}", s)))
+		};
+	}
+
+	/// Get the host name or IP address
+	pub fn get_host(&self) -> &str {
+		return &self.host;
+	}
+
+	/// Get the port
+	pub fn get_port(&self) -> u16 {
+		return self.port;
+	}
+}
+
+impl fmt::Display for Authority {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.host.contains(':') {
+			return write!(f, "[{}]:{}", self.host, self.port);
+		}
+		return write!(f, "{}:{}", self.host, self.port);
+	}
+}
+
+/// Values which can be converted to an `Authority`, like `"www.example.com:80"`, `("www.example.com", 80)` or a `SocketAddr`
+pub trait ToAuthority {
+	/// Convert this value to an `Authority`
+	fn to_authority(&self) -> Result<Authority, Error>;
+}
+
+impl ToAuthority for Authority {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(self.clone());
+	}
+}
+
+impl ToAuthority for str {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Authority::parse(self);
+	}
+}
+
+impl ToAuthority for String {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Authority::parse(self);
+	}
+}
+
+impl ToAuthority for SocketAddr {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(&self.ip().to_string(), self.port()));
+	}
+}
+
+impl ToAuthority for SocketAddrV4 {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(&self.ip().to_string(), self.port()));
+	}
+}
+
+impl ToAuthority for SocketAddrV6 {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(&self.ip().to_string(), self.port()));
+	}
+}
+
+impl <'a> ToAuthority for (&'a str, u16) {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(self.0, self.1));
+	}
+}
+
+impl ToAuthority for (IpAddr, u16) {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(&self.0.to_string(), self.1));
+	}
+}
+
+impl ToAuthority for (Ipv4Addr, u16) {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(&self.0.to_string(), self.1));
+	}
+}
+
+impl ToAuthority for (Ipv6Addr, u16) {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return Ok(Authority::new(&self.0.to_string(), self.1));
+	}
+}
+
+impl <'a, T: ToAuthority + ?Sized> ToAuthority for &'a T {
+	fn to_authority(&self) -> Result<Authority, Error> {
+		return (**self).to_authority();
+	}
+}
+
+/// Resolve host names to socket addresses
+pub trait Resolver: Send + Sync {
+	/// Get the addresses of `host`, with the given `port`. IP addresses resolve to themselves
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error>;
+}
+
+impl <R: Resolver + ?Sized> Resolver for Arc<R> {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
+		return (**self).resolve(host, port);
+	}
+}
+
+impl <R: Resolver + ?Sized> Resolver for Box<R> {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
+		return (**self).resolve(host, port);
+	}
+}
+
+/// The resolver of the operating system, used by clients by default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
+		let addrs: Vec<SocketAddr> = try!((host, port).to_socket_addrs()).collect();
+		if addrs.is_empty() {
+			return Err(Error::new(ErrorKind::Other, format!("Cannot resolve {}", host)));
+		}
+		return Ok(addrs);
+	}
+}
+
+/// A resolver keeping the addresses resolved by another one for a fixed duration. Failures are not cached
+pub struct CachingResolver<R: Resolver> {
+	inner: R,
+	ttl: Duration,
+	cache: Mutex<HashMap<(String, u16), (Instant, Vec<SocketAddr>)>>
+}
+
+impl <R: Resolver> CachingResolver<R> {
+	/// Create a new resolver keeping the addresses resolved by `inner` during `ttl`
+	pub fn new(inner: R, ttl: Duration) -> CachingResolver<R> {
+		return CachingResolver{inner: inner, ttl: ttl, cache: Mutex::new(HashMap::new())};
+	}
+
+	fn lock(&self) -> MutexGuard<HashMap<(String, u16), (Instant, Vec<SocketAddr>)>> {
+		return match self.cache.lock() {
+			Ok(c) => c,
+			Err(e) => e.into_inner()
+		};
+	}
+
+	/// Forget all the cached addresses
+	pub fn clear(&self) {
+		self.lock().clear();
+	}
+}
+
+impl <R: Resolver> Resolver for CachingResolver<R> {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
+		let key = (host.to_ascii_lowercase(), port);
+		if let Some(&(resolved, ref addrs)) = self.lock().get(&key) {
+			if resolved.elapsed() < self.ttl {
+				return Ok(addrs.clone());
+			}
+		}
+		// The lock is not held while resolving, which may be slow
+		let addrs = try!(self.inner.resolve(host, port));
+		self.lock().insert(key, (Instant::now(), addrs.clone()));
+		return Ok(addrs);
+	}
+}
+
+/// A resolver returning fixed addresses for some hosts, and delegating the others to another resolver
+pub struct StaticResolver<R: Resolver> {
+	overrides: HashMap<(String, u16), Vec<SocketAddr>>,
+	fallback: R
+}
+
+impl <R: Resolver> StaticResolver<R> {
+	/// Create a new resolver without overrides, delegating to `fallback`
+	pub fn new(fallback: R) -> StaticResolver<R> {
+		return StaticResolver{overrides: HashMap::new(), fallback: fallback};
+	}
+
+	/// Resolve `host` with `port` to `addr`. Several addresses can be added for the same host and port
+	pub fn add(mut self, host: &str, port: u16, addr: IpAddr) -> StaticResolver<R> {
+		self.overrides.entry((host.to_ascii_lowercase(), port)).or_insert_with(Vec::new).push(SocketAddr::new(addr, port));
+		return self;
+	}
+
+	/// Add overrides given like curl's `--resolve` option, as `host:port:addr[,addr]...`,
+	/// with IPv6 addresses optionally enclosed in brackets
+	/// # Example
+	/// ```
+	/// use http::resolve::{Resolver, StaticResolver, SystemResolver};
+	///
+	/// let resolver = StaticResolver::new(SystemResolver).add_override("example.com:443:127.0.0.1,[::1]").unwrap();
+	/// let addrs = resolver.resolve("example.com", 443).unwrap();
+	/// assert_eq!(addrs, vec!["127.0.0.1:443".parse().unwrap(), "[::1]:443".parse().unwrap()]);
+	/// ```
+	pub fn add_override(mut self, entry: &str) -> Result<StaticResolver<R>, Error> {
+		let mut splt = entry.splitn(3, ':');
+		let (host, port, addrs) = match (splt.next(), splt.next(), splt.next()) {
+			(Some(h), Some(p), Some(a)) if !h.is_empty() => (h, p, a),
+			_ => return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid resolve override : {}", entry)))
+		};
+		let port = match u16::from_str(port) {
+			Ok(p) => p,
+			Err(_) => return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid port in resolve override : {}", entry)))
+		};
+		for addr in addrs.split(',') {
+			let addr = addr.trim().trim_matches(|c| c == '[' || c == ']');
+			match IpAddr::from_str(addr) {
+				Ok(ip) => self = self.add(host, port, ip),
+				Err(_) => return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid address in resolve override : {}", entry)))
+			}
+		}
+		return Ok(self);
+	}
+}
+
+impl <R: Resolver> Resolver for StaticResolver<R> {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
+		return match self.overrides.get(&(host.to_ascii_lowercase(), port)) {
+			Some(addrs) => Ok(addrs.clone()),
+			None => self.fallback.resolve(host, port)
+		};
+	}
+}
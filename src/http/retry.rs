@@ -0,0 +1,199 @@
+//! Retry layer with exponential backoff
+//!
+//! A `RetryClient` stands in front of any `Execute` implementation, and sends requests again when they
+//! fail with a transient error, like a refused connection or a keep-alive connection closed by the server,
+//! or when the reply has one of the configured status codes.
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use http::client::*;
+//! use http::methods::Method;
+//! use http::request::HttpRequest;
+//! use http::retry::{RetryClient, RetryPolicy};
+//!
+//! let client = HttpClient::new("www.example.com:80").unwrap();
+//! let policy = RetryPolicy::new().max_attempts(5).budget(Duration::from_secs(30));
+//! let mut client = RetryClient::new(client, policy);
+//! let reply = client.execute(&HttpRequest::new(Method::GET, "/flaky")).unwrap();
+//! # drop(reply);
+//! ```
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+use std::thread;
+
+use super::client::Execute;
+use super::messages::BufferedReply;
+use super::request::HttpRequest;
+use super::headers::RetryAfter;
+use super::random;
+use super::trace;
+
+/// Rules deciding whether and when a request is sent again
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+	max_attempts: u32,
+	base_delay: Duration,
+	max_delay: Duration,
+	budget: Option<Duration>,
+	statuses: Vec<u32>,
+	non_idempotent: bool
+}
+
+impl RetryPolicy {
+	/// Create the default policy : at most 3 attempts, with delays starting at 100ms and limited to 10s,
+	/// retrying `429`, `502`, `503` and `504` replies
+	pub fn new() -> RetryPolicy {
+		return RetryPolicy{
+			max_attempts: 3,
+			base_delay: Duration::from_millis(100),
+			max_delay: Duration::from_secs(10),
+			budget: None,
+			statuses: vec![429, 502, 503, 504],
+			non_idempotent: false
+		};
+	}
+
+	/// Set the maximum number of attempts, including the first one
+	pub fn max_attempts(mut self, attempts: u32) -> RetryPolicy {
+		self.max_attempts = attempts;
+		return self;
+	}
+
+	/// Set the delay before the first retry, doubled after each attempt up to `max`.
+	/// Replies asking with `Retry-After` to wait longer than `max` are not retried
+	pub fn backoff(mut self, base: Duration, max: Duration) -> RetryPolicy {
+		self.base_delay = base;
+		self.max_delay = max;
+		return self;
+	}
+
+	/// Set the maximum time spent on a request, including all its attempts and the delays between them.
+	/// No retry is attempted if it would exceed the budget
+	pub fn budget(mut self, budget: Duration) -> RetryPolicy {
+		self.budget = Some(budget);
+		return self;
+	}
+
+	/// Set the reply status codes which are retried
+	pub fn statuses(mut self, statuses: &[u32]) -> RetryPolicy {
+		self.statuses = statuses.to_vec();
+		return self;
+	}
+
+	/// Allow retrying requests with non idempotent methods, like `POST`. Disabled by default, since the
+	/// server may have processed the request before failing
+	pub fn retry_non_idempotent(mut self, enabled: bool) -> RetryPolicy {
+		self.non_idempotent = enabled;
+		return self;
+	}
+
+	/// Return true if `request` may be sent again
+	pub fn can_retry(&self, request: &HttpRequest) -> bool {
+		return self.non_idempotent || request.get_method().is_idempotent();
+	}
+
+	/// Return true if `error` is transient, and the request may succeed if sent again
+	pub fn is_retryable_error(&self, error: &Error) -> bool {
+		return match error.kind() {
+			ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted |
+			ErrorKind::NotConnected | ErrorKind::BrokenPipe | ErrorKind::UnexpectedEof |
+			ErrorKind::TimedOut | ErrorKind::Interrupted | ErrorKind::AddrNotAvailable => true,
+			_ => false
+		};
+	}
+
+	/// Return true if `reply` has one of the retried status codes
+	pub fn is_retryable_reply(&self, reply: &BufferedReply) -> bool {
+		return self.statuses.contains(&reply.get_code());
+	}
+
+	/// Get the delay before sending the request again, after the given number of failed `attempts`.
+	///
+	/// The delay of the `Retry-After` property of `reply` is used if there is one. Otherwise,
+	/// the delay grows exponentially, and is randomized between half and all of its value.
+	pub fn delay(&self, attempts: u32, reply: Option<&BufferedReply>) -> Duration {
+		if let Some(retry_after) = reply.and_then(|r| r.get_typed::<RetryAfter>()) {
+			return retry_after.delay();
+		}
+		let factor = 1u32.checked_shl(attempts.saturating_sub(1)).unwrap_or(u32::max_value());
+		let delay = match self.base_delay.checked_mul(factor) {
+			Some(d) if d < self.max_delay => d,
+			_ => self.max_delay
+		};
+		let millis = delay.as_secs() * 1000 + delay.subsec_nanos() as u64 / 1000000;
+		return Duration::from_millis(millis / 2 + random::next_u64() % (millis - millis / 2 + 1));
+	}
+}
+
+/// An `Execute` implementation retrying requests according to a `RetryPolicy`
+///
+/// When attempts are exhausted, the last error or reply is returned.
+pub struct RetryClient<E: Execute> {
+	inner: E,
+	policy: RetryPolicy
+}
+
+impl <E: Execute> RetryClient<E> {
+	/// Create a new retry layer in front of `inner`
+	pub fn new(inner: E, policy: RetryPolicy) -> RetryClient<E> {
+		return RetryClient{inner: inner, policy: policy};
+	}
+
+	/// Get the retry policy
+	pub fn get_policy(&self) -> &RetryPolicy {
+		return &self.policy;
+	}
+
+	/// Replace the retry policy
+	pub fn set_policy(&mut self, policy: RetryPolicy) {
+		self.policy = policy;
+	}
+
+	/// Get a reference to the wrapped client
+	pub fn get_ref(&self) -> &E {
+		return &self.inner;
+	}
+
+	/// Get a mutable reference to the wrapped client
+	pub fn get_mut(&mut self) -> &mut E {
+		return &mut self.inner;
+	}
+
+	/// Unwrap the retry layer, returning the wrapped client
+	pub fn into_inner(self) -> E {
+		return self.inner;
+	}
+}
+
+impl <E: Execute> Execute for RetryClient<E> {
+	fn get_host(&self) -> Option<String> {
+		return self.inner.get_host();
+	}
+
+	fn execute(&mut self, request: &HttpRequest) -> Result<BufferedReply, Error> {
+		let start = Instant::now();
+		let mut attempts = 0;
+		loop {
+			let res = self.inner.execute(request);
+			attempts += 1;
+			let retry = match res {
+				Ok(ref reply) => self.policy.is_retryable_reply(reply),
+				Err(ref e) => self.policy.is_retryable_error(e)
+			};
+			if !retry || attempts >= self.policy.max_attempts || !self.policy.can_retry(request) {
+				return res;
+			}
+			let delay = self.policy.delay(attempts, res.as_ref().ok());
+			if delay > self.policy.max_delay {
+				return res;
+			}
+			if let Some(budget) = self.policy.budget {
+				if start.elapsed() + delay > budget {
+					return res;
+				}
+			}
+			trace::retry(request, attempts, delay, &res);
+			thread::sleep(delay);
+		}
+	}
+}
@@ -1,52 +1,445 @@
 //! Http I/O streams definitions
 
-use std::net::{ToSocketAddrs, TcpStream};
-use std::io::{Error, Read, Write};
-#[cfg(feature="ssl")]
-use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, TcpStream};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::trace::{self, Transfer};
+use super::timing::{Timings, SharedTimings};
+use super::metrics::ConnectionMetrics;
 
 /// Represent a type that can be opened (ie connected) to a remote `SocketAddress`
 pub trait Open: Sized {
 	/// Create a new Instance of `Self` connected to `addr`
 	fn open<A: ToSocketAddrs>(addr: A) -> Result<Self, Error>;
+
+	/// Create a new Instance of `Self` connected to `addr`, within the given `timeouts`.
+	/// By default, timeouts are ignored
+	fn open_with<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts) -> Result<Self, Error> {
+		let _ = timeouts;
+		return Self::open(addr);
+	}
+
+	/// Create a new Instance of `Self` connected to `addr`, within the given `timeouts`, and record the
+	/// end of the connection phases in `timings`. By default, the connection is recorded once `open_with` returns
+	fn open_timed<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts, timings: &mut Timings) -> Result<Self, Error> {
+		let stream = try!(Self::open_with(addr, timeouts));
+		timings.mark_connected();
+		return Ok(stream);
+	}
 }
 
 /// A trait representing an openable read/write stream
-pub trait Stream: Read+Write+Open {}
+pub trait Stream: Read+Write+Open {
+	/// Set the maximum duration a single read or write operation can block. `None` means no timeout.
+	/// By default, timeouts are ignored
+	fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> Result<(), Error> {
+		let _ = (read, write);
+		return Ok(());
+	}
+}
+
+/// Timeouts applied to connections and requests. All of them are disabled by default
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+/// use http::client::HttpClient;
+/// use http::streams::Timeouts;
+///
+/// let mut client = HttpClient::new("www.example.com:80").unwrap();
+/// client.set_timeouts(Timeouts::new()
+/// 	.connect(Duration::from_secs(2))
+/// 	.read(Duration::from_secs(10))
+/// 	.total(Duration::from_secs(30)));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timeouts {
+	connect: Option<Duration>,
+	read: Option<Duration>,
+	write: Option<Duration>,
+	total: Option<Duration>
+}
+
+impl Timeouts {
+	/// Create a new set of timeouts, all disabled
+	pub fn new() -> Timeouts {
+		return Timeouts::default();
+	}
+
+	/// Set the maximum duration of a connection attempt, applied to each resolved address.
+	///
+	/// Without it, a host resolving to a single address is given as long as the system allows, while the
+	/// attempts raced between the several addresses of a host are each limited to 30 seconds
+	pub fn connect(mut self, timeout: Duration) -> Timeouts {
+		self.connect = Some(timeout);
+		return self;
+	}
+
+	/// Set the maximum duration a single read can block, including during TLS handshakes
+	pub fn read(mut self, timeout: Duration) -> Timeouts {
+		self.read = Some(timeout);
+		return self;
+	}
+
+	/// Set the maximum duration a single write can block, including during TLS handshakes
+	pub fn write(mut self, timeout: Duration) -> Timeouts {
+		self.write = Some(timeout);
+		return self;
+	}
+
+	/// Set the maximum duration of a whole request, from connection to the end of the reply.
+	/// It is overridden by the timeout of the request, if any
+	pub fn total(mut self, timeout: Duration) -> Timeouts {
+		self.total = Some(timeout);
+		return self;
+	}
+
+	/// Get the connection timeout
+	pub fn get_connect(&self) -> Option<Duration> {
+		return self.connect;
+	}
+
+	/// Get the read timeout
+	pub fn get_read(&self) -> Option<Duration> {
+		return self.read;
+	}
+
+	/// Get the write timeout
+	pub fn get_write(&self) -> Option<Duration> {
+		return self.write;
+	}
+
+	/// Get the total request timeout
+	pub fn get_total(&self) -> Option<Duration> {
+		return self.total;
+	}
+
+	/// Get these timeouts, shortened so none of them goes beyond `deadline`.
+	/// Fails with a `TimedOut` error if `deadline` has passed
+	pub fn until(&self, deadline: Option<Instant>) -> Result<Timeouts, Error> {
+		let remaining = match deadline {
+			Some(d) => Some(try!(remaining(d))),
+			None => return Ok(*self)
+		};
+		return Ok(Timeouts{
+			connect: shortest(self.connect, remaining),
+			read: shortest(self.read, remaining),
+			write: shortest(self.write, remaining),
+			total: remaining
+		});
+	}
+}
+
+/// Return true if `error` is caused by a timeout
+pub fn is_timeout(error: &Error) -> bool {
+	return error.kind() == ErrorKind::TimedOut;
+}
+
+fn shortest(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+	return match (a, b) {
+		(Some(a), Some(b)) => Some(if a < b { a } else { b }),
+		(a, None) => a,
+		(None, b) => b
+	};
+}
+
+fn remaining(deadline: Instant) -> Result<Duration, Error> {
+	let now = Instant::now();
+	if now >= deadline {
+		return Err(Error::new(ErrorKind::TimedOut, "Request deadline exceeded"));
+	}
+	return Ok(deadline - now);
+}
+
+/// Convert errors caused by expired socket timeouts, which are reported as `WouldBlock` on some platforms, to `TimedOut` errors
+fn timed_out(error: Error, operation: &str) -> Error {
+	return match error.kind() {
+		ErrorKind::WouldBlock | ErrorKind::TimedOut => Error::new(ErrorKind::TimedOut, format!("{} timed out", operation)),
+		_ => error
+	};
+}
+
+/// Delay before starting the next connection attempt while the previous ones are pending, in milliseconds (RFC 8305 section 5)
+const ATTEMPT_DELAY_MS: u64 = 250;
+
+/// Timeout of the raced connection attempts when no connect timeout is given, in seconds, so that the
+/// attempts left behind by the winner do not hold their thread until the system gives up
+const RACE_ATTEMPT_TIMEOUT_SECS: u64 = 30;
+
+/// Order `addrs` for connection attempts, alternating between address families and starting with the
+/// family of the first address, whose preference is given by the resolver (RFC 8305 section 4)
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+	let first_v6 = match addrs.first() {
+		Some(a) => a.is_ipv6(),
+		None => return addrs
+	};
+	let (preferred, other): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter().partition(|a| a.is_ipv6() == first_v6);
+	let mut sorted = Vec::with_capacity(preferred.len() + other.len());
+	let mut other = other.into_iter();
+	for a in preferred {
+		sorted.push(a);
+		sorted.extend(other.next());
+	}
+	sorted.extend(other);
+	return sorted;
+}
+
+/// Attempt a connection to `addr` within `timeout`
+fn attempt(addr: &SocketAddr, timeout: Option<Duration>) -> Result<TcpStream, Error> {
+	let res = match timeout {
+		Some(t) => TcpStream::connect_timeout(addr, t),
+		None => TcpStream::connect(addr)
+	};
+	return res.map_err(|e| timed_out(e, "Connection"));
+}
+
+/// Connect to the first reachable address of `addr`, each attempt being given `timeout`
+///
+/// When `addr` resolves to several addresses, they are raced following the Happy Eyeballs algorithm (RFC 8305) :
+/// attempts alternate between IPv6 and IPv4 addresses, and each of them is started when the previous one fails,
+/// or 250ms after it has started if it is still pending. The first established connection is used, and the
+/// connections established later are closed.
+///
+/// If `timeout` is `None`, raced attempts are still limited to 30 seconds each, so that the attempts left
+/// pending once a connection is established don't keep their thread blocked until the system gives up.
+fn connect_tcp<A: ToSocketAddrs>(addr: A, timeout: Option<Duration>) -> Result<TcpStream, Error> {
+	let addrs = interleave(try!(addr.to_socket_addrs()).collect());
+	if addrs.len() == 1 {
+		let res = attempt(&addrs[0], timeout);
+		match res {
+			Ok(_) => trace::connected(&addrs[0]),
+			Err(ref e) => trace::connect_failed(&addrs[0], e)
+		}
+		return res;
+	}
+	let timeout = Some(timeout.unwrap_or(Duration::from_secs(RACE_ATTEMPT_TIMEOUT_SECS)));
+	let (tx, rx) = mpsc::channel();
+	let mut waiting = addrs.into_iter();
+	let mut pending = 0;
+	let mut last_error = None;
+	loop {
+		if let Some(a) = waiting.next() {
+			let tx = tx.clone();
+			// A failure to report means that another attempt has already succeeded
+			thread::spawn(move || { let _ = tx.send((a, attempt(&a, timeout))); });
+			pending += 1;
+		}
+		if pending == 0 {
+			break;
+		}
+		// Wait for a pending attempt to complete, until the next one is due
+		let res = if waiting.len() > 0 {
+			match rx.recv_timeout(Duration::from_millis(ATTEMPT_DELAY_MS)) {
+				Ok(r) => r,
+				Err(RecvTimeoutError::Timeout) => continue,
+				Err(RecvTimeoutError::Disconnected) => break
+			}
+		} else {
+			match rx.recv() {
+				Ok(r) => r,
+				Err(_) => break
+			}
+		};
+		pending -= 1;
+		match res {
+			(a, Ok(s)) => {
+				trace::connected(&a);
+				return Ok(s);
+			},
+			(a, Err(e)) => {
+				trace::connect_failed(&a, &e);
+				last_error = Some(e);
+			}
+		}
+	}
+	return Err(last_error.unwrap_or(Error::new(ErrorKind::InvalidInput, "Cannot resolve address")));
+}
+
+/// A stream enforcing read and write timeouts, and an optional deadline
+///
+/// It records the timings of the request it carries. The bytes transferred are reported when the stream
+/// is dropped, if the "log" or "tracing" feature is enabled. Expired timeouts are reported as `TimedOut` errors, whatever the platform.
+pub struct TimedStream<S: Stream> {
+	inner: S,
+	read: Option<Duration>,
+	write: Option<Duration>,
+	deadline: Option<Instant>,
+	timings: SharedTimings,
+	metrics: Option<ConnectionMetrics>,
+	_transfer: Transfer
+}
+
+impl <S: Stream> TimedStream<S> {
+	/// Wrap `inner`, applying the read and write `timeouts`
+	pub fn new(inner: S, timeouts: &Timeouts) -> Result<TimedStream<S>, Error> {
+		try!(inner.set_timeouts(timeouts.read, timeouts.write));
+		let timings = SharedTimings::new(Timings::new());
+		let transfer = Transfer::new(timings.clone());
+		return Ok(TimedStream{inner: inner, read: timeouts.read, write: timeouts.write, deadline: None, timings: timings, metrics: None, _transfer: transfer});
+	}
+
+	/// Set the instant after which every read or write fails
+	pub fn set_deadline(&mut self, deadline: Option<Instant>) -> Result<(), Error> {
+		self.deadline = deadline;
+		if deadline.is_none() {
+			try!(self.inner.set_timeouts(self.read, self.write));
+		}
+		return Ok(());
+	}
+
+	/// Keep `metrics` until this stream is dropped, so the connection is counted open until then
+	pub fn observe(&mut self, metrics: ConnectionMetrics) {
+		self.metrics = Some(metrics);
+	}
+
+	/// Replace the timings updated by the reads and writes on this stream, like the ones recorded while connecting
+	pub fn set_timings(&mut self, timings: Timings) {
+		*self.timings.lock() = timings;
+	}
+
+	/// Get the timings updated by the reads and writes on this stream
+	pub fn get_timings(&self) -> &SharedTimings {
+		return &self.timings;
+	}
+
+	/// Get a reference to the underlying stream
+	pub fn get_ref(&self) -> &S {
+		return &self.inner;
+	}
+
+	/// Get a mutable reference to the underlying stream
+	pub fn get_mut(&mut self) -> &mut S {
+		return &mut self.inner;
+	}
+
+	/// Unwrap this stream, returning the underlying one
+	pub fn into_inner(self) -> S {
+		return self.inner;
+	}
+
+	/// Shorten the socket timeouts so the next operation doesn't block beyond the deadline
+	fn apply_deadline(&self) -> Result<(), Error> {
+		if let Some(deadline) = self.deadline {
+			let remaining = Some(try!(remaining(deadline)));
+			try!(self.inner.set_timeouts(shortest(self.read, remaining), shortest(self.write, remaining)));
+		}
+		return Ok(());
+	}
+}
+
+impl <S: Stream> Read for TimedStream<S> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		try!(self.apply_deadline());
+		let n = try!(self.inner.read(buf).map_err(|e| timed_out(e, "Read")));
+		self.timings.lock().record_read(n);
+		return Ok(n);
+	}
+}
+
+impl <S: Stream> Write for TimedStream<S> {
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		try!(self.apply_deadline());
+		let n = try!(self.inner.write(buf).map_err(|e| timed_out(e, "Write")));
+		self.timings.lock().record_write(n);
+		return Ok(n);
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		try!(self.apply_deadline());
+		return self.inner.flush().map_err(|e| timed_out(e, "Write"));
+	}
+}
 
 /// HttpStream for unsecured HTTP Input/Output
 pub type HttpStream = TcpStream;
-impl Stream for HttpStream{}
+impl Stream for HttpStream {
+	fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> Result<(), Error> {
+		try!(self.set_read_timeout(read));
+		return self.set_write_timeout(write);
+	}
+}
 
 impl Open for HttpStream {
 	fn open<A: ToSocketAddrs>(addr: A) -> Result<HttpStream, Error> {
 		return Ok(try!(TcpStream::connect(addr)));
 	}
+
+	fn open_with<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts) -> Result<HttpStream, Error> {
+		return connect_tcp(addr, timeouts.connect);
+	}
+
+	fn open_timed<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts, timings: &mut Timings) -> Result<HttpStream, Error> {
+		let sock = try!(connect_tcp(addr, timeouts.connect));
+		timings.mark_connected();
+		if let Ok(a) = sock.peer_addr() {
+			timings.set_remote_addr(a);
+		}
+		return Ok(sock);
+	}
 }
 
 #[cfg(feature="ssl")]
-use openssl::ssl::{SslStream, SslMethod, SslConnectorBuilder, SslVerifyMode};
+use openssl::ssl::{SslStream, SslMethod, SslConnectorBuilder, SslVerifyMode, HandshakeError};
 
 /// HttpsStream for secured HTTPS Input/Output. Only available if "ssl" feature is enabled
 #[cfg(feature="ssl")]
 pub type HttpsStream = SslStream<TcpStream>;
 #[cfg(feature="ssl")]
-impl Stream for HttpsStream{}
+impl Stream for HttpsStream {
+	fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> Result<(), Error> {
+		try!(self.get_ref().set_read_timeout(read));
+		return self.get_ref().set_write_timeout(write);
+	}
+}
+
+/// Perform the TLS handshake over `sock`
+#[cfg(feature="ssl")]
+fn handshake(sock: TcpStream) -> Result<HttpsStream, Error> {
+	let addr = sock.peer_addr().ok();
+	let res = tls_connect(sock);
+	trace::tls_handshake(addr, res.as_ref().err());
+	return res;
+}
+
+/// Negotiate TLS over `sock`
+#[cfg(feature="ssl")]
+fn tls_connect(sock: TcpStream) -> Result<HttpsStream, Error> {
+	let mut builder = match SslConnectorBuilder::new(SslMethod::tls()) {
+		Ok(s) => s,
+		Err(e) => return Err(Error::new(ErrorKind::Other, format!("Cannot create SSL connector : {}", e)))
+	};
+	//TODO: Do not skip verifications
+	builder.builder_mut().set_verify(SslVerifyMode::empty());
+	return match builder.build().danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication(sock) {
+		Ok(s) => Ok(s),
+		// An expired socket timeout interrupts the handshake
+		Err(HandshakeError::Interrupted(_)) => Err(Error::new(ErrorKind::TimedOut, "TLS handshake timed out")),
+		Err(e) => Err(Error::new(ErrorKind::Other, format!("Cannot create SSL stream : {}", e)))
+	};
+}
 
 #[cfg(feature="ssl")]
 impl Open for HttpsStream {
 	fn open<A: ToSocketAddrs>(addr: A) -> Result<HttpsStream, Error> {
-		let sock = try!(TcpStream::connect(addr));
-		let mut builder = match SslConnectorBuilder::new(SslMethod::tls()) {
-			Ok(s) => s,
-			Err(e) => return Err(Error::new(ErrorKind::Other, format!("Cannot create SSL connector : {}", e)))
-		};
-		//TODO: Do not skip verifications
-		builder.builder_mut().set_verify(SslVerifyMode::empty());
-		let stream = match builder.build().danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication(sock) {
-			Ok(s) => s,
-			Err(e) => return Err(Error::new(ErrorKind::Other, format!("Cannot create SSL stream : {}", e)))
-		};
+		return handshake(try!(TcpStream::connect(addr)));
+	}
+
+	fn open_with<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts) -> Result<HttpsStream, Error> {
+		return HttpsStream::open_timed(addr, timeouts, &mut Timings::new());
+	}
+
+	fn open_timed<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts, timings: &mut Timings) -> Result<HttpsStream, Error> {
+		let sock = try!(connect_tcp(addr, timeouts.connect));
+		timings.mark_connected();
+		if let Ok(a) = sock.peer_addr() {
+			timings.set_remote_addr(a);
+		}
+		try!(sock.set_read_timeout(timeouts.read));
+		try!(sock.set_write_timeout(timeouts.write));
+		let stream = try!(handshake(sock));
+		timings.mark_tls();
 		return Ok(stream);
 	}
 }
@@ -0,0 +1,137 @@
+//! Common syntax elements of header values
+use std::io::{Error, ErrorKind};
+
+/// Return true if `b` is a valid token character
+pub fn is_token(b: u8) -> bool {
+	return b > 32 && b < 127 && !b"()<>@,;:\\\"/[]?={}".contains(&b);
+}
+
+/// A simple parser for header values made of tokens, quoted strings and parameters
+pub struct Parser<'a> {
+	s: &'a [u8],
+	pos: usize
+}
+
+impl <'a> Parser<'a> {
+	/// Create a new parser over `s`
+	pub fn new(s: &'a str) -> Parser<'a> {
+		return Parser{s: s.as_bytes(), pos: 0};
+	}
+
+	/// Skip spaces and tabs
+	pub fn skip_spaces(&mut self) {
+		while self.pos < self.s.len() && (self.s[self.pos] == b' ' || self.s[self.pos] == b'\t') {
+			self.pos += 1;
+		}
+	}
+
+	/// Consume `c` if it is the next non blank character
+	pub fn eat(&mut self, c: u8) -> bool {
+		self.skip_spaces();
+		if self.pos < self.s.len() && self.s[self.pos] == c {
+			self.pos += 1;
+			return true;
+		}
+		return false;
+	}
+
+	/// Return true if there is nothing left but blanks
+	pub fn at_end(&mut self) -> bool {
+		self.skip_spaces();
+		return self.pos >= self.s.len();
+	}
+
+	/// Read a token, which may be empty
+	pub fn token(&mut self) -> String {
+		self.skip_spaces();
+		let start = self.pos;
+		while self.pos < self.s.len() && is_token(self.s[self.pos]) {
+			self.pos += 1;
+		}
+		return String::from_utf8_lossy(&self.s[start..self.pos]).into_owned();
+	}
+
+	/// Read a quoted string starting at current position
+	pub fn quoted(&mut self) -> Result<String, Error> {
+		let mut value = Vec::new();
+		self.pos += 1;
+		while self.pos < self.s.len() {
+			match self.s[self.pos] {
+				b'"' => {
+					self.pos += 1;
+					return Ok(String::from_utf8_lossy(&value).into_owned());
+				},
+				b'\\' if self.pos + 1 < self.s.len() => {
+					value.push(self.s[self.pos + 1]);
+					self.pos += 2;
+				},
+				c => {
+					value.push(c);
+					self.pos += 1;
+				}
+			}
+		}
+		return Err(Error::new(ErrorKind::InvalidData, "Unterminated quoted string"));
+	}
+
+	/// Read a token or a quoted string
+	pub fn value(&mut self) -> Result<String, Error> {
+		self.skip_spaces();
+		if self.pos < self.s.len() && self.s[self.pos] == b'"' {
+			return self.quoted();
+		}
+		return Ok(self.token());
+	}
+
+	/// Read everything up to `c`, which is consumed, or up to the end of input
+	pub fn until(&mut self, c: u8) -> String {
+		let start = self.pos;
+		while self.pos < self.s.len() && self.s[self.pos] != c {
+			self.pos += 1;
+		}
+		let s = String::from_utf8_lossy(&self.s[start..self.pos]).into_owned();
+		if self.pos < self.s.len() {
+			self.pos += 1;
+		}
+		return s;
+	}
+
+	/// Parse `; name=value` parameters, until the end of input or a `,`
+	pub fn params(&mut self) -> Result<Vec<(String, String)>, Error> {
+		let mut params = Vec::new();
+		while self.eat(b';') {
+			let name = self.token().to_ascii_lowercase();
+			if name.is_empty() {
+				continue;
+			}
+			if !self.eat(b'=') {
+				return Err(Error::new(ErrorKind::InvalidData, format!("Missing value for parameter {}", name)));
+			}
+			let value = try!(self.value());
+			params.push((name, value));
+		}
+		return Ok(params);
+	}
+}
+
+/// Split a comma separated list, ignoring commas in quoted strings
+pub fn split_list(s: &str) -> Vec<&str> {
+	let mut items = Vec::new();
+	let mut quoted = false;
+	let mut escaped = false;
+	let mut start = 0;
+	for (i, c) in s.char_indices() {
+		match c {
+			_ if escaped => escaped = false,
+			'\\' if quoted => escaped = true,
+			'"' => quoted = !quoted,
+			',' if !quoted => {
+				items.push(s[start..i].trim());
+				start = i + 1;
+			},
+			_ => {}
+		}
+	}
+	items.push(s[start..].trim());
+	return items.into_iter().filter(|i| !i.is_empty()).collect();
+}
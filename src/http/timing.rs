@@ -0,0 +1,218 @@
+//! Per-request timings and transfer statistics
+//!
+//! The replies read by clients carry the `Timings` of their request, available with `HttpReply::get_timings()`.
+//! All the instants are given as durations since the start of the request, like curl's `-w` variables.
+//! # Example
+//! ```no_run
+//! use http::client::*;
+//! use http::methods::Method;
+//!
+//! let mut client = HttpClient::new("www.example.com:80").unwrap();
+//! let mut reply = client.send(Method::GET, "/", None, None).unwrap();
+//! reply.read_all().unwrap();
+//! let timings = reply.get_timings().unwrap();
+//! println!("First byte after {:?}, {} bytes received from {:?}", timings.get_first_byte(), timings.get_received(), timings.get_remote_addr());
+//! println!("{}", timings);
+//! ```
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Timestamps of the phases of a request, and the number of bytes it transferred
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timings {
+	start: Instant,
+	resolved: Option<Instant>,
+	connected: Option<Instant>,
+	tls: Option<Instant>,
+	request_written: Option<Instant>,
+	first_byte: Option<Instant>,
+	last_byte: Option<Instant>,
+	body_complete: Option<Instant>,
+	sent: u64,
+	received: u64,
+	remote: Option<SocketAddr>
+}
+
+impl Timings {
+	/// Create new timings for a request starting now
+	pub fn new() -> Timings {
+		return Timings{
+			start: Instant::now(),
+			resolved: None,
+			connected: None,
+			tls: None,
+			request_written: None,
+			first_byte: None,
+			last_byte: None,
+			body_complete: None,
+			sent: 0,
+			received: 0,
+			remote: None
+		};
+	}
+
+	/// Record that the host name has been resolved
+	pub fn mark_resolved(&mut self) {
+		self.resolved = Some(Instant::now());
+	}
+
+	/// Record that the TCP connection has been established
+	pub fn mark_connected(&mut self) {
+		self.connected = Some(Instant::now());
+	}
+
+	/// Record that the TLS handshake has completed
+	pub fn mark_tls(&mut self) {
+		self.tls = Some(Instant::now());
+	}
+
+	/// Record that the reply content has been entirely received, when its last byte was read
+	pub fn mark_body_complete(&mut self) {
+		if self.body_complete.is_none() {
+			self.body_complete = self.last_byte;
+		}
+	}
+
+	/// Record `bytes` written to the connection. Writes until the first byte of the reply belong to the request
+	pub fn record_write(&mut self, bytes: usize) {
+		self.sent += bytes as u64;
+		if self.first_byte.is_none() {
+			self.request_written = Some(Instant::now());
+		}
+	}
+
+	/// Record `bytes` read from the connection
+	pub fn record_read(&mut self, bytes: usize) {
+		if bytes == 0 {
+			return;
+		}
+		let now = Instant::now();
+		self.received += bytes as u64;
+		if self.first_byte.is_none() {
+			self.first_byte = Some(now);
+		}
+		self.last_byte = Some(now);
+	}
+
+	/// Set the address the connection has been established with
+	pub fn set_remote_addr(&mut self, addr: SocketAddr) {
+		self.remote = Some(addr);
+	}
+
+	fn since_start(&self, instant: Option<Instant>) -> Option<Duration> {
+		return instant.map(|i| i.duration_since(self.start));
+	}
+
+	/// Get the instant the request started
+	pub fn get_start(&self) -> Instant {
+		return self.start;
+	}
+
+	/// Get the time spent resolving the host name
+	pub fn get_resolve(&self) -> Option<Duration> {
+		return self.since_start(self.resolved);
+	}
+
+	/// Get the time until the TCP connection was established
+	pub fn get_connect(&self) -> Option<Duration> {
+		return self.since_start(self.connected);
+	}
+
+	/// Get the time until the TLS handshake completed. `None` for unsecured connections
+	pub fn get_tls(&self) -> Option<Duration> {
+		return self.since_start(self.tls);
+	}
+
+	/// Get the time until the request, including its body, was written
+	pub fn get_request_written(&self) -> Option<Duration> {
+		return self.since_start(self.request_written);
+	}
+
+	/// Get the time until the first byte of the reply was received
+	pub fn get_first_byte(&self) -> Option<Duration> {
+		return self.since_start(self.first_byte);
+	}
+
+	/// Get the time until the last byte received so far
+	pub fn get_last_byte(&self) -> Option<Duration> {
+		return self.since_start(self.last_byte);
+	}
+
+	/// Get the time until the reply content was entirely received. `None` until the content has been read
+	pub fn get_body_complete(&self) -> Option<Duration> {
+		return self.since_start(self.body_complete);
+	}
+
+	/// Get the number of bytes sent, including the request line and header
+	pub fn get_sent(&self) -> u64 {
+		return self.sent;
+	}
+
+	/// Get the number of bytes received, including the status line and header
+	pub fn get_received(&self) -> u64 {
+		return self.received;
+	}
+
+	/// Return true if the request has been sent on a connection already used by previous requests.
+	/// Always false for now, since clients open a new connection for each request
+	pub fn is_reused(&self) -> bool {
+		return false;
+	}
+
+	/// Get the address the connection has been established with, among the resolved addresses of the host
+	pub fn get_remote_addr(&self) -> Option<SocketAddr> {
+		return self.remote;
+	}
+}
+
+impl fmt::Display for Timings {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let phases = [
+			("resolve", self.get_resolve()),
+			("connect", self.get_connect()),
+			("tls", self.get_tls()),
+			("request_written", self.get_request_written()),
+			("first_byte", self.get_first_byte()),
+			("body_complete", self.get_body_complete())
+		];
+		for &(name, time) in phases.iter() {
+			match time {
+				Some(d) => try!(write!(f, "{}={}.{:06}s ", name, d.as_secs(), d.subsec_nanos() / 1000)),
+				None => try!(write!(f, "{}=- ", name))
+			}
+		}
+		try!(write!(f, "sent={} received={} reused={}", self.sent, self.received, self.is_reused()));
+		return match self.remote {
+			Some(a) => write!(f, " remote={}", a),
+			None => write!(f, " remote=-")
+		};
+	}
+}
+
+/// A handle on the timings of a request, updated by the connection while the request is sent and its reply is read
+#[derive(Clone)]
+pub struct SharedTimings {
+	inner: Arc<Mutex<Timings>>
+}
+
+impl SharedTimings {
+	/// Create a new handle on `timings`
+	pub fn new(timings: Timings) -> SharedTimings {
+		return SharedTimings{inner: Arc::new(Mutex::new(timings))};
+	}
+
+	/// Lock the timings for update
+	pub fn lock(&self) -> MutexGuard<Timings> {
+		return match self.inner.lock() {
+			Ok(t) => t,
+			Err(e) => e.into_inner()
+		};
+	}
+
+	/// Get a copy of the current timings
+	pub fn get(&self) -> Timings {
+		return *self.lock();
+	}
+}
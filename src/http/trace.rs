@@ -0,0 +1,209 @@
+//! Diagnostic events, emitted through the `log` and `tracing` crates when the corresponding features are enabled
+//!
+//! Events are emitted with the `http` target. With the "tracing" feature, each request sent by a client
+//! opens a `request` span, which encloses the events of its connection and of its reply.
+use std::fmt;
+use std::io::{Error, Read};
+#[cfg(not(feature="tracing"))]
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use super::request::HttpRequest;
+use super::messages::{HttpReply, BufferedReply};
+use super::timing::SharedTimings;
+#[cfg(any(feature="log", feature="tracing"))]
+use super::client::WithHeader;
+
+/// Properties whose value is never emitted
+#[cfg(any(feature="log", feature="tracing"))]
+const REDACTED: [&'static str; 4] = ["authorization", "proxy-authorization", "cookie", "x-api-key"];
+
+/// Format the properties of `head`, hiding the credentials
+#[cfg(any(feature="log", feature="tracing"))]
+fn redacted_header(head: &HttpRequest) -> String {
+	let mut props: Vec<String> = head.iter().map(|(k, v)| {
+		if REDACTED.contains(&&k.to_ascii_lowercase()[..]) {
+			format!("{}: <redacted>", k)
+		} else {
+			format!("{}: {}", k, v)
+		}
+	}).collect();
+	props.sort();
+	return props.join(", ");
+}
+
+/// The span of a request
+#[derive(Clone)]
+pub struct Span {
+	#[cfg(feature="tracing")]
+	inner: ::tracing::Span
+}
+
+impl Span {
+	/// A span which is never entered
+	pub fn none() -> Span {
+		return Span{
+			#[cfg(feature="tracing")]
+			inner: ::tracing::Span::none()
+		};
+	}
+
+	/// The span the current thread is in
+	pub fn current() -> Span {
+		return Span{
+			#[cfg(feature="tracing")]
+			inner: ::tracing::Span::current()
+		};
+	}
+
+	/// Open the span of the request described by `head`, sent to `host`
+	pub fn request(head: &HttpRequest, host: &str) -> Span {
+		let _ = (head, host);
+		return Span{
+			#[cfg(feature="tracing")]
+			inner: ::tracing::debug_span!(target: "http", "request", method = %head.get_method(), path = head.get_target(), host = host)
+		};
+	}
+
+	/// Enter the span until the returned guard is dropped
+	pub fn enter(&self) -> Entered {
+		return Entered{
+			#[cfg(feature="tracing")]
+			_inner: self.inner.enter(),
+			#[cfg(not(feature="tracing"))]
+			_span: PhantomData
+		};
+	}
+}
+
+/// A guard keeping a span entered
+pub struct Entered<'a> {
+	#[cfg(feature="tracing")]
+	_inner: ::tracing::span::Entered<'a>,
+	#[cfg(not(feature="tracing"))]
+	_span: PhantomData<&'a Span>
+}
+
+/// A message of the deprecated `debug!` macro, written at `line` of `file`. Without the "log" and "tracing"
+/// features, it is printed if the calling crate has been built with `debug_assertions`
+pub fn debug_message(debug_assertions: bool, file: &str, line: u32, message: fmt::Arguments) {
+	let _ = (debug_assertions, file, line, &message);
+	#[cfg(feature="log")]
+	::log::debug!(target: "http", "[{} line {}] {}", file, line, message);
+	#[cfg(feature="tracing")]
+	::tracing::debug!(target: "http", file = file, line = line, "{}", message);
+	#[cfg(not(any(feature="log", feature="tracing")))]
+	{
+		if debug_assertions {
+			println!("[DEBUG {} line {}] {}", file, line, message);
+		}
+	}
+}
+
+/// A TCP connection to `addr` has been established
+pub fn connected(addr: &SocketAddr) {
+	let _ = addr;
+	#[cfg(feature="log")]
+	::log::debug!(target: "http", "Connected to {}", addr);
+	#[cfg(feature="tracing")]
+	::tracing::debug!(target: "http", addr = %addr, "Connected");
+}
+
+/// A TCP connection to `addr` has failed
+pub fn connect_failed(addr: &SocketAddr, error: &Error) {
+	let _ = (addr, error);
+	#[cfg(feature="log")]
+	::log::debug!(target: "http", "Connection to {} failed : {}", addr, error);
+	#[cfg(feature="tracing")]
+	::tracing::debug!(target: "http", addr = %addr, error = %error, "Connection failed");
+}
+
+/// A TLS handshake with `addr` has completed, or failed with `error`
+#[cfg(feature="ssl")]
+pub fn tls_handshake(addr: Option<SocketAddr>, error: Option<&Error>) {
+	let addr = addr.map(|a| a.to_string()).unwrap_or("?".to_string());
+	let _ = (&addr, error);
+	#[cfg(feature="log")]
+	match error {
+		None => ::log::debug!(target: "http", "TLS handshake with {} completed", addr),
+		Some(e) => ::log::debug!(target: "http", "TLS handshake with {} failed : {}", addr, e)
+	}
+	#[cfg(feature="tracing")]
+	match error {
+		None => ::tracing::debug!(target: "http", addr = %addr, "TLS handshake completed"),
+		Some(e) => ::tracing::debug!(target: "http", addr = %addr, error = %e, "TLS handshake failed")
+	}
+}
+
+/// The request line and the header of `head` have been written. Credentials are redacted
+pub fn request(head: &HttpRequest) {
+	let _ = head;
+	#[cfg(feature="log")]
+	{
+		::log::debug!(target: "http", "{} {} {}", head.get_method(), head.get_target(), head.get_version());
+		::log::trace!(target: "http", "Request header : {}", redacted_header(head));
+	}
+	#[cfg(feature="tracing")]
+	{
+		::tracing::debug!(target: "http", method = %head.get_method(), path = head.get_target(), version = head.get_version(), "Request sent");
+		::tracing::trace!(target: "http", header = %redacted_header(head), "Request header");
+	}
+}
+
+/// The status line and the header of a reply have been read
+pub fn reply<T: Read>(reply: &Result<HttpReply<T>, Error>) {
+	let _ = reply;
+	#[cfg(feature="log")]
+	match *reply {
+		Ok(ref r) => ::log::debug!(target: "http", "{} {} {}", r.get_version(), r.get_code(), r.get_status()),
+		Err(ref e) => ::log::debug!(target: "http", "Invalid reply : {}", e)
+	}
+	#[cfg(feature="tracing")]
+	match *reply {
+		Ok(ref r) => ::tracing::debug!(target: "http", code = r.get_code(), status = r.get_status(), "Reply received"),
+		Err(ref e) => ::tracing::debug!(target: "http", error = %e, "Invalid reply")
+	}
+}
+
+/// `request` will be sent again after `delay`, because its `attempt`-th attempt has given `outcome`
+pub fn retry(request: &HttpRequest, attempt: u32, delay: Duration, outcome: &Result<BufferedReply, Error>) {
+	let _ = (request, attempt, delay, outcome);
+	#[cfg(any(feature="log", feature="tracing"))]
+	{
+		let reason = match *outcome {
+			Ok(ref r) => format!("{} {}", r.get_code(), r.get_status()),
+			Err(ref e) => e.to_string()
+		};
+		let millis = delay.as_secs() * 1000 + delay.subsec_nanos() as u64 / 1000000;
+		#[cfg(feature="log")]
+		::log::info!(target: "http", "Retrying {} {} in {}ms after attempt {} : {}", request.get_method(), request.get_target(), millis, attempt, reason);
+		#[cfg(feature="tracing")]
+		::tracing::info!(target: "http", method = %request.get_method(), path = request.get_target(), attempt = attempt, delay_ms = millis, reason = %reason, "Retrying request");
+	}
+}
+
+/// Bytes transferred on a connection, reported when the connection is closed
+pub struct Transfer {
+	span: Span,
+	timings: SharedTimings
+}
+
+impl Transfer {
+	/// Report the bytes counted in `timings`, for a connection opened in the current span
+	pub fn new(timings: SharedTimings) -> Transfer {
+		return Transfer{span: Span::current(), timings: timings};
+	}
+}
+
+impl Drop for Transfer {
+	fn drop(&mut self) {
+		let _entered = self.span.enter();
+		let timings = self.timings.get();
+		let _ = timings;
+		#[cfg(feature="log")]
+		::log::debug!(target: "http", "Connection closed, {} bytes sent, {} bytes received", timings.get_sent(), timings.get_received());
+		#[cfg(feature="tracing")]
+		::tracing::debug!(target: "http", sent = timings.get_sent(), received = timings.get_received(), "Connection closed");
+	}
+}
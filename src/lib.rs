@@ -4,7 +4,33 @@
 //!
 //! Feature "ssl" enable secured connection support with openssl.
 //! This feature is enabled by default but can be disabled
+//!
+//! Features "gzip", "deflate", "brotli" and "zstd" enable decoding of replies content encoded
+//! with the corresponding content coding.
+//!
+//! Feature "json" enables JSON request and reply bodies serialization with serde.
+//!
+//! Feature "charset" enables decoding of replies text in all the WHATWG encodings.
+//!
+//! Features "log" and "tracing" make clients emit diagnostic events, like connections, requests and
+//! replies, through the corresponding crates, with the `http` target. Credentials are never emitted.
 #[cfg(feature="ssl")]
 extern crate openssl;
+#[cfg(any(feature="gzip", feature="deflate"))]
+extern crate flate2;
+#[cfg(feature="brotli")]
+extern crate brotli_decompressor;
+#[cfg(feature="zstd")]
+extern crate zstd;
+#[cfg(feature="json")]
+extern crate serde;
+#[cfg(feature="json")]
+extern crate serde_json;
+#[cfg(feature="charset")]
+extern crate encoding_rs;
+#[cfg(feature="log")]
+extern crate log;
+#[cfg(feature="tracing")]
+extern crate tracing;
 mod http;
 pub use http::*;
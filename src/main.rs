@@ -7,7 +7,7 @@ use http::methods::Method;
 use http::client::*;
 //use http::{open, Protocol};
 
-#[allow(dead_code)]
+#[allow(dead_code, deprecated)]
 fn main() {
 	let mut http = HttpsClient::new("www.google.com:443").unwrap();
 //	let mut http = HttpClient::new("www.google.com:80").unwrap();